@@ -0,0 +1,99 @@
+use actix_web::{http::StatusCode, HttpRequest, HttpResponse, ResponseError};
+use serde::Serialize;
+use std::fmt;
+use sustechcourse::{export::ExportError, CourseError, LoginError};
+
+/// Every error comes back as `{"error": kind, "message": text}`.
+#[derive(Debug)]
+pub enum ApiError {
+    Course(CourseError),
+    Export(ExportError),
+    /// Invalid query, body or header.
+    BadRequest(String),
+    /// Missing or unknown bearer token.
+    Unauthorized(String),
+    NotFound,
+    /// The resource exists but not with this method.
+    MethodNotAllowed,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: &'static str,
+    message: String,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApiError::Course(err) => err.fmt(f),
+            ApiError::Export(err) => err.fmt(f),
+            ApiError::BadRequest(message) | ApiError::Unauthorized(message) =>
+                f.write_str(message),
+            ApiError::NotFound => f.write_str("no such resource"),
+            ApiError::MethodNotAllowed => f.write_str("method not allowed on this resource"),
+        }
+    }
+}
+
+impl From<CourseError> for ApiError {
+    fn from(err: CourseError) -> Self {
+        ApiError::Course(err)
+    }
+}
+
+impl From<ExportError> for ApiError {
+    fn from(err: ExportError) -> Self {
+        ApiError::Export(err)
+    }
+}
+
+impl ApiError {
+    fn kind(&self) -> (StatusCode, &'static str) {
+        let err = match self {
+            ApiError::Course(err) => err,
+            ApiError::Export(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "export"),
+            ApiError::BadRequest(_) => return (StatusCode::BAD_REQUEST, "bad_request"),
+            ApiError::Unauthorized(_) => return (StatusCode::UNAUTHORIZED, "unauthorized"),
+            ApiError::NotFound => return (StatusCode::NOT_FOUND, "not_found"),
+            ApiError::MethodNotAllowed =>
+                return (StatusCode::METHOD_NOT_ALLOWED, "method_not_allowed"),
+        };
+        match err {
+            CourseError::Login(LoginError::ServiceUnavailable(_)) =>
+                (StatusCode::SERVICE_UNAVAILABLE, "unavailable"),
+            CourseError::Login(_) => (StatusCode::UNAUTHORIZED, "login"),
+            CourseError::SessionExpired => (StatusCode::UNAUTHORIZED, "session_expired"),
+            CourseError::Network(err) if err.is_timeout()
+                || err.status().is_some_and(|status| status.is_server_error()) =>
+                (StatusCode::SERVICE_UNAVAILABLE, "unavailable"),
+            CourseError::Network(_) => (StatusCode::BAD_GATEWAY, "network"),
+            CourseError::Parse { .. } => (StatusCode::INTERNAL_SERVER_ERROR, "parse"),
+            CourseError::Config(_) => (StatusCode::INTERNAL_SERVER_ERROR, "config"),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.kind().0
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let (status, error) = self.kind();
+        HttpResponse::build(status).json(ErrorBody { error, message: self.to_string() })
+    }
+}
+
+/// Report a malformed JSON body or query string of actix in our format.
+pub fn bad_request<E: fmt::Display>(err: E, _: &HttpRequest) -> actix_web::Error {
+    ApiError::BadRequest(err.to_string()).into()
+}
+
+pub async fn not_found() -> Result<HttpResponse, ApiError> {
+    Err(ApiError::NotFound)
+}
+
+pub async fn method_not_allowed() -> Result<HttpResponse, ApiError> {
+    Err(ApiError::MethodNotAllowed)
+}
@@ -0,0 +1,175 @@
+use actix_web::{
+    web, HttpServer, HttpRequest, HttpResponse, App,
+    http::header::ACCEPT, middleware::Logger,
+};
+use serde::Deserialize;
+use log::info;
+use std::time::Duration;
+use sustechcourse::{
+    credits, export::{Column, Exporter, Format}, Endpoints, LoginedAgent, UserAgent,
+};
+
+mod error;
+mod sessions;
+mod v1;
+
+use crate::{error::ApiError, sessions::Sessions};
+
+type AgentSessions = Sessions<LoginedAgent>;
+
+/// Seconds a login token stays valid since its last use.
+const DEFAULT_SESSION_TTL: u64 = 30 * 60;
+/// Seconds a calendar feed stays valid since last fetched, which drops
+/// abandoned feeds; calendar apps fetch subscribed ones at least daily.
+const DEFAULT_FEED_TTL: u64 = 7 * 24 * 60 * 60;
+/// Seconds a calendar feed lasts since subscribed, however often fetched,
+/// about one term. Feeds keep the password to log in again in memory, so
+/// this bounds how long it is kept; a restart drops every feed anyway.
+const DEFAULT_FEED_MAX_AGE: u64 = 20 * 7 * 24 * 60 * 60;
+/// How often expired sessions and feeds are dropped from memory.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize)]
+struct CourseQueryInfo {
+    username: String,
+    password: String,
+}
+
+/// Options of the course list, such as `?format=csv&columns=code,score&sort=term`.
+#[derive(Deserialize)]
+struct ExportQuery {
+    /// Overrides the `Accept` header.
+    format: Option<String>,
+    columns: Option<String>,
+    sort: Option<String>,
+    /// "asc" or "desc".
+    order: Option<String>,
+}
+
+/// A fresh agent with its own cookies.
+fn new_agent(endpoints: &Endpoints) -> UserAgent {
+    UserAgent::new().with_endpoints(endpoints.clone())
+}
+
+/// Pick the format from the query, or else the first known type in
+/// `Accept`, defaulting to JSON. Quality values are not considered.
+fn exporter(req: &HttpRequest, query: &ExportQuery) -> Result<Exporter, ApiError> {
+    let bad_request = |err: &dyn std::fmt::Display| ApiError::BadRequest(err.to_string());
+    let format = match &query.format {
+        Some(format) => format.parse().map_err(|err| bad_request(&err))?,
+        None => req.headers().get_all(ACCEPT)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .find_map(Format::from_media_type)
+            .unwrap_or(Format::Json),
+    };
+    let columns = |list: &str| -> Result<Vec<Column>, ApiError> {
+        list.split(',').map(|name| name.parse().map_err(|err| bad_request(&err))).collect()
+    };
+    let mut exporter = Exporter::new(format);
+    if let Some(list) = &query.columns {
+        exporter = exporter.columns(columns(list)?);
+    }
+    if let Some(list) = &query.sort {
+        exporter = exporter.sort_by(columns(list)?);
+    }
+    match query.order.as_deref() {
+        None | Some("asc") => (),
+        Some("desc") => exporter = exporter.descending(true),
+        Some(order) => return Err(ApiError::BadRequest(format!("unknown order {}", order))),
+    }
+    Ok(exporter)
+}
+
+/// Deprecated, login with `/v1/login` and use `/v1/courses` instead.
+async fn query_course(
+    req: HttpRequest,
+    query: web::Query<ExportQuery>,
+    info: web::Json<CourseQueryInfo>,
+    endpoints: web::Data<Endpoints>,
+) -> Result<HttpResponse, ApiError> {
+    let exporter = exporter(&req, &query)?;
+    let mut agent = new_agent(&endpoints)
+        .login(info.username.clone(), info.password.clone())
+        .await?;
+    let courses = agent.all_courses().await?;
+    let body = exporter.to_string(&courses)?;
+    Ok(HttpResponse::Ok().content_type(exporter.format().content_type()).body(body))
+}
+
+/// Deprecated, use `/v1/credits` instead.
+async fn query_credits(info: web::Json<CourseQueryInfo>, endpoints: web::Data<Endpoints>)
+    -> Result<web::Json<credits::Summary>, ApiError>
+{
+    let mut agent = new_agent(&endpoints)
+        .login(info.username.clone(), info.password.clone())
+        .await?;
+    let courses = agent.all_courses().await?;
+    Ok(web::Json(credits::summarize(&courses)))
+}
+
+/// Routes from before `/v1`, kept for old clients. Token routes are
+/// aliases of their `/v1` counterparts.
+fn deprecated(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/").route(web::post().to(query_course)))
+        .service(web::resource("/login").route(web::post().to(v1::login)))
+        .service(web::resource("/logout").route(web::post().to(v1::logout)))
+        .service(web::resource("/courses").route(web::get().to(v1::courses)))
+        .service(web::resource("/gpa").route(web::get().to(v1::gpa)))
+        .service(web::resource("/credits")
+            .route(web::get().to(v1::credits))
+            .route(web::post().to(query_credits)));
+}
+
+/// SUSTech, or the CAS and jsxsd given in environment variables.
+fn endpoints() -> Endpoints {
+    match (std::env::var("SUSTECH_CAS_LOGIN"), std::env::var("SUSTECH_JSXSD")) {
+        (Ok(cas_login), Ok(jsxsd)) => Endpoints::new(&cas_login, &jsxsd)
+            .expect("invalid SUSTECH_CAS_LOGIN or SUSTECH_JSXSD"),
+        _ => Endpoints::default(),
+    }
+}
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    //std::env::set_var("RUST_LOG", "actix_web=info");
+    env_logger::init();
+
+    let bind = std::env::var("HTTP_BIND")
+        .unwrap_or("127.0.0.1:8000".to_string());
+    let ttl = |name: &str, default: u64| std::env::var(name).ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(default);
+    let sessions = web::Data::new(AgentSessions::new(
+        Duration::from_secs(ttl("SESSION_TTL", DEFAULT_SESSION_TTL))));
+    let feeds = web::Data::new(v1::Feeds::new(
+        Duration::from_secs(ttl("FEED_TTL", DEFAULT_FEED_TTL)))
+        .with_max_age(Duration::from_secs(ttl("FEED_MAX_AGE", DEFAULT_FEED_MAX_AGE))));
+    let endpoints = web::Data::new(endpoints());
+    let (expiring_sessions, expiring_feeds) = (sessions.clone(), feeds.clone());
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            expiring_sessions.purge();
+            expiring_feeds.purge();
+        }
+    });
+    info!("Start server on {}", bind);
+
+    HttpServer::new(move ||
+        App::new()
+            .wrap(Logger::default())
+            .app_data(sessions.clone())
+            .app_data(feeds.clone())
+            .app_data(endpoints.clone())
+            .app_data(web::JsonConfig::default().error_handler(error::bad_request))
+            .app_data(web::QueryConfig::default().error_handler(error::bad_request))
+            .service(web::scope("/v1").configure(v1::config))
+            .configure(deprecated)
+            .default_service(web::to(error::not_found))
+    ).bind(bind)
+        .expect("Can not bind to port 8000")
+        .run()
+        .await
+}
@@ -0,0 +1,144 @@
+use rand::{rngs::OsRng, RngCore};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const TOKEN_BYTES: usize = 32;
+
+/// Logged-in agents by opaque bearer token, each dropped once unused for
+/// the TTL, or once older than the max age however much it is used.
+#[derive(Debug)]
+pub struct Sessions<T> {
+    ttl: Duration,
+    max_age: Option<Duration>,
+    entries: Mutex<HashMap<String, Entry<T>>>,
+}
+
+#[derive(Debug)]
+struct Entry<T> {
+    value: T,
+    expires: Instant,
+    /// End of the max age, which use does not extend.
+    deadline: Option<Instant>,
+}
+
+impl<T: Clone> Sessions<T> {
+    pub fn new(ttl: Duration) -> Self {
+        Sessions { ttl, max_age: None, entries: Mutex::default() }
+    }
+
+    /// Drop each entry this long after its insertion, used or not.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age
+    }
+
+    /// Keep the value, returning a new token of it.
+    pub fn insert(&self, value: T) -> String {
+        self.insert_at(value, Instant::now())
+    }
+
+    /// The value of an unexpired token, extending it by the TTL up to the
+    /// max age.
+    pub fn get(&self, token: &str) -> Option<T> {
+        self.get_at(token, Instant::now())
+    }
+
+    /// Drop expired entries, which are otherwise kept until their token
+    /// is used or another one is inserted.
+    pub fn purge(&self) {
+        self.purge_at(Instant::now())
+    }
+
+    fn insert_at(&self, value: T, now: Instant) -> String {
+        let mut bytes = [0; TOKEN_BYTES];
+        OsRng.fill_bytes(&mut bytes);
+        let token: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+        let deadline = self.max_age.map(|max_age| now + max_age);
+        let mut entry = Entry { value, expires: now, deadline };
+        entry.extend(self.ttl, now);
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, entry| entry.expires > now);
+        entries.insert(token.clone(), entry);
+        token
+    }
+
+    fn get_at(&self, token: &str, now: Instant) -> Option<T> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get_mut(token) {
+            Some(entry) if entry.expires > now => {
+                entry.extend(self.ttl, now);
+                Some(entry.value.clone())
+            }
+            Some(_) => {
+                entries.remove(token);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn purge_at(&self, now: Instant) {
+        self.entries.lock().unwrap().retain(|_, entry| entry.expires > now);
+    }
+
+    /// Drop the token, returning whether it existed.
+    pub fn remove(&self, token: &str) -> bool {
+        self.entries.lock().unwrap().remove(token).is_some()
+    }
+}
+
+impl<T> Entry<T> {
+    /// Expire the TTL after now, but not past the deadline.
+    fn extend(&mut self, ttl: Duration, now: Instant) {
+        let expires = now + ttl;
+        self.expires = self.deadline.map_or(expires, |deadline| expires.min(deadline));
+    }
+}
+
+#[test]
+fn test_sessions() {
+    let ms = Duration::from_millis;
+    let start = Instant::now();
+    let sessions = Sessions::new(ms(100));
+    let token = sessions.insert_at(1, start);
+    assert_eq!(token.len(), TOKEN_BYTES * 2);
+    assert_ne!(sessions.insert_at(2, start), token);
+    assert_eq!(sessions.get_at(&token, start), Some(1));
+    assert_eq!(sessions.get_at("unknown", start), None);
+
+    assert_eq!(sessions.get_at(&token, start + ms(60)), Some(1));
+    assert_eq!(sessions.get_at(&token, start + ms(120)), Some(1), "not extended on use");
+    assert_eq!(sessions.get_at(&token, start + ms(210)), Some(1));
+    assert_eq!(sessions.get_at(&token, start + ms(320)), None);
+
+    sessions.insert_at(4, start);
+    sessions.purge_at(start + ms(100));
+    assert!(sessions.entries.lock().unwrap().is_empty());
+
+    let token = sessions.insert(3);
+    assert!(sessions.remove(&token));
+    assert!(!sessions.remove(&token));
+    assert_eq!(sessions.get(&token), None);
+}
+
+#[test]
+fn test_sessions_max_age() {
+    let ms = Duration::from_millis;
+    let start = Instant::now();
+    let sessions = Sessions::new(ms(100)).with_max_age(ms(250));
+    let token = sessions.insert_at(1, start);
+    assert_eq!(sessions.get_at(&token, start + ms(90)), Some(1));
+    assert_eq!(sessions.get_at(&token, start + ms(180)), Some(1));
+    assert_eq!(sessions.get_at(&token, start + ms(240)), Some(1));
+    assert_eq!(sessions.get_at(&token, start + ms(250)), None, "extended past max age");
+}
@@ -0,0 +1,302 @@
+//! Routes under `/v1`, authenticated by the bearer token from `/v1/login`,
+//! except for calendar feeds which carry their own token in the path.
+//!
+//! Calendar feeds live in memory only. Restarting the server drops all
+//! of them, and their URLs answer 404 until subscribed again.
+use actix_web::{http::header::AUTHORIZATION, web, HttpRequest, HttpResponse, Resource};
+use chrono::NaiveDate;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use sustechcourse::{
+    calendar::{self, Calendar}, credits, gpa, ClassSession, Course, CourseError, Endpoints,
+    Exam, LoginedAgent, Term,
+};
+
+use crate::{
+    error::{self, ApiError}, exporter, new_agent, sessions::Sessions, AgentSessions, CourseQueryInfo,
+    ExportQuery,
+};
+
+/// Subscribed timetables by the token in their URL.
+pub type Feeds = Sessions<Feed>;
+
+const CALENDAR_CONTENT_TYPE: &str = "text/calendar; charset=utf-8";
+
+/// A timetable subscription. Its agent logs in again with the saved
+/// password whenever jwxt drops the session, so the password stays in
+/// memory, in plain text, until the feed expires or is deleted. Fetches
+/// extend a feed up to its max age since subscribed, not beyond.
+#[derive(Clone)]
+pub struct Feed {
+    agent: LoginedAgent,
+    term: Term,
+    start: NaiveDate,
+}
+
+#[derive(Serialize)]
+pub struct LoginBody {
+    token: String,
+    /// Seconds until the token expires if unused.
+    expires_in: u64,
+}
+
+/// Filters of `/v1/courses`, matching fields of `Course` exactly.
+#[derive(Deserialize)]
+pub struct CourseFilter {
+    term: Option<String>,
+    #[serde(rename = "type")]
+    course_type: Option<String>,
+    category: Option<String>,
+}
+
+/// `start` is the Monday of week 1, such as 2018-09-10. If given,
+/// the timetable comes as iCalendar instead of JSON.
+#[derive(Deserialize)]
+struct TimetableQuery {
+    term: String,
+    start: Option<NaiveDate>,
+}
+
+/// `format` is "json", the default, or "ics".
+#[derive(Deserialize)]
+struct ExamQuery {
+    term: String,
+    format: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FeedInfo {
+    username: String,
+    password: String,
+    term: String,
+    start: NaiveDate,
+}
+
+#[derive(Serialize)]
+struct FeedBody {
+    url: String,
+    /// The same URL for calendar apps, with the `webcal` scheme.
+    webcal: String,
+    /// Seconds until the feed expires if not fetched.
+    expires_in: u64,
+    /// Seconds until the feed ends however often fetched, after which it
+    /// has to be subscribed again.
+    max_age: Option<u64>,
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(resource("/login").route(web::post().to(login)))
+        .service(resource("/logout").route(web::post().to(logout)))
+        .service(resource("/courses").route(web::get().to(courses)))
+        .service(resource("/terms").route(web::get().to(terms)))
+        .service(resource("/gpa").route(web::get().to(gpa)))
+        .service(resource("/credits").route(web::get().to(credits)))
+        .service(resource("/timetable").route(web::get().to(timetable)))
+        .service(resource("/exams").route(web::get().to(exams)))
+        .service(resource("/calendars").route(web::post().to(subscribe)))
+        .service(resource("/calendars/{token}.ics")
+            .route(web::get().to(feed))
+            .route(web::delete().to(unsubscribe)))
+        .default_service(web::to(error::not_found));
+}
+
+/// A resource answering other methods with our JSON error, as actix
+/// does not pass them on to the default service of the scope.
+fn resource(path: &str) -> Resource {
+    web::resource(path).default_service(web::to(error::method_not_allowed))
+}
+
+fn parse_term(term: &str) -> Result<Term, ApiError> {
+    term.parse().map_err(|err| ApiError::BadRequest(format!("{}", err)))
+}
+
+fn calendar_response(term: Term, start: NaiveDate, sessions: &[ClassSession]) -> HttpResponse {
+    let calendar = Calendar::new(start).name(&term.to_string());
+    HttpResponse::Ok().content_type(CALENDAR_CONTENT_TYPE).body(calendar.to_string(sessions))
+}
+
+impl CourseFilter {
+    fn matches(&self, term: Option<Term>, course: &Course) -> bool {
+        term.is_none_or(|term| course.term == term)
+            && self.course_type.as_ref().is_none_or(|value| &course.course_type == value)
+            && self.category.as_ref().is_none_or(|value| &course.category == value)
+    }
+}
+
+fn bearer_token(req: &HttpRequest) -> Result<&str, ApiError> {
+    req.headers().get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .ok_or_else(|| ApiError::Unauthorized("missing bearer token".into()))
+}
+
+fn session_agent<'a>(req: &'a HttpRequest, sessions: &AgentSessions)
+    -> Result<(&'a str, LoginedAgent), ApiError>
+{
+    let token = bearer_token(req)?;
+    let agent = sessions.get(token)
+        .ok_or_else(|| ApiError::Unauthorized("invalid or expired token".into()))?;
+    Ok((token, agent))
+}
+
+/// Drop the token once jwxt expires its session.
+fn check_expired<T>(result: Result<T, CourseError>, token: &str, sessions: &AgentSessions)
+    -> Result<T, ApiError>
+{
+    if let Err(CourseError::SessionExpired) = result {
+        debug!("jwxt session of a token expired");
+        sessions.remove(token);
+    }
+    Ok(result?)
+}
+
+/// All courses of the session of the bearer token.
+async fn session_courses(req: &HttpRequest, sessions: &AgentSessions)
+    -> Result<Vec<Course>, ApiError>
+{
+    let (token, mut agent) = session_agent(req, sessions)?;
+    check_expired(agent.all_courses().await, token, sessions)
+}
+
+pub async fn login(
+    info: web::Json<CourseQueryInfo>,
+    endpoints: web::Data<Endpoints>,
+    sessions: web::Data<AgentSessions>,
+) -> Result<web::Json<LoginBody>, ApiError> {
+    let agent = new_agent(&endpoints)
+        .login(info.username.clone(), info.password.clone())
+        .await?;
+    let token = sessions.insert(agent);
+    Ok(web::Json(LoginBody { token, expires_in: sessions.ttl().as_secs() }))
+}
+
+pub async fn logout(req: HttpRequest, sessions: web::Data<AgentSessions>)
+    -> Result<HttpResponse, ApiError>
+{
+    if sessions.remove(bearer_token(&req)?) {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(ApiError::Unauthorized("invalid or expired token".into()))
+    }
+}
+
+pub async fn courses(
+    req: HttpRequest,
+    filter: web::Query<CourseFilter>,
+    query: web::Query<ExportQuery>,
+    sessions: web::Data<AgentSessions>,
+) -> Result<HttpResponse, ApiError> {
+    let exporter = exporter(&req, &query)?;
+    let term = match &filter.term {
+        Some(term) => Some(parse_term(term)?),
+        None => None,
+    };
+    let mut courses = session_courses(&req, &sessions).await?;
+    courses.retain(|course| filter.matches(term, course));
+    let body = exporter.to_string(&courses)?;
+    Ok(HttpResponse::Ok().content_type(exporter.format().content_type()).body(body))
+}
+
+/// Terms offered on the query form.
+async fn terms(req: HttpRequest, sessions: web::Data<AgentSessions>)
+    -> Result<web::Json<Vec<Term>>, ApiError>
+{
+    let (token, agent) = session_agent(&req, &sessions)?;
+    let query = check_expired(agent.query_course().await, token, &sessions)?;
+    Ok(web::Json(query.years().to_vec()))
+}
+
+pub async fn gpa(req: HttpRequest, sessions: web::Data<AgentSessions>)
+    -> Result<web::Json<gpa::Report>, ApiError>
+{
+    let courses = session_courses(&req, &sessions).await?;
+    Ok(web::Json(gpa::Rules::default().report(&courses)))
+}
+
+pub async fn credits(req: HttpRequest, sessions: web::Data<AgentSessions>)
+    -> Result<web::Json<credits::Summary>, ApiError>
+{
+    let courses = session_courses(&req, &sessions).await?;
+    Ok(web::Json(credits::summarize(&courses)))
+}
+
+async fn timetable(
+    req: HttpRequest,
+    query: web::Query<TimetableQuery>,
+    sessions: web::Data<AgentSessions>,
+) -> Result<HttpResponse, ApiError> {
+    let term = parse_term(&query.term)?;
+    let (token, agent) = session_agent(&req, &sessions)?;
+    let classes = check_expired(agent.timetable(term).await, token, &sessions)?;
+    Ok(match query.start {
+        Some(start) => calendar_response(term, start, &classes),
+        None => HttpResponse::Ok().json(classes),
+    })
+}
+
+async fn exams(
+    req: HttpRequest,
+    query: web::Query<ExamQuery>,
+    sessions: web::Data<AgentSessions>,
+) -> Result<HttpResponse, ApiError> {
+    let term = parse_term(&query.term)?;
+    let ics = match query.format.as_deref() {
+        None | Some("json") => false,
+        Some("ics") => true,
+        Some(format) => return Err(ApiError::BadRequest(format!("unknown format {}", format))),
+    };
+    let (token, agent) = session_agent(&req, &sessions)?;
+    let exams: Vec<Exam> = check_expired(agent.exams(term).await, token, &sessions)?;
+    Ok(if ics {
+        let name = format!("{} exams", term);
+        HttpResponse::Ok()
+            .content_type(CALENDAR_CONTENT_TYPE)
+            .body(calendar::exams_to_string(&exams, Some(&name)))
+    } else {
+        HttpResponse::Ok().json(exams)
+    })
+}
+
+/// Create a calendar URL to subscribe to from a phone. It stays valid
+/// while fetched at least once per feed TTL, or until deleted.
+async fn subscribe(
+    req: HttpRequest,
+    info: web::Json<FeedInfo>,
+    endpoints: web::Data<Endpoints>,
+    feeds: web::Data<Feeds>,
+) -> Result<web::Json<FeedBody>, ApiError> {
+    let term = parse_term(&info.term)?;
+    let agent = new_agent(&endpoints)
+        .login(info.username.clone(), info.password.clone())
+        .await?
+        .relogin_with((info.username.clone(), info.password.clone()));
+    let token = feeds.insert(Feed { agent, term, start: info.start });
+
+    let conn = req.connection_info();
+    let path = format!("{}/v1/calendars/{}.ics", conn.host(), token);
+    Ok(web::Json(FeedBody {
+        url: format!("{}://{}", conn.scheme(), path),
+        webcal: format!("webcal://{}", path),
+        expires_in: feeds.ttl().as_secs(),
+        max_age: feeds.max_age().map(|max_age| max_age.as_secs()),
+    }))
+}
+
+async fn feed(token: web::Path<String>, feeds: web::Data<Feeds>)
+    -> Result<HttpResponse, ApiError>
+{
+    let Feed { agent, term, start } = feeds.get(&token).ok_or(ApiError::NotFound)?;
+    let classes = agent.timetable(term).await?;
+    Ok(calendar_response(term, start, &classes))
+}
+
+async fn unsubscribe(token: web::Path<String>, feeds: web::Data<Feeds>)
+    -> Result<HttpResponse, ApiError>
+{
+    if feeds.remove(&token) {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(ApiError::NotFound)
+    }
+}
@@ -0,0 +1,96 @@
+//! TOML file of the account, the watcher and where to send changes to,
+//! such as:
+//!
+//! ```toml
+//! [account]
+//! username = "11510000"
+//! password = "secret"
+//!
+//! [watch]
+//! interval = 600
+//!
+//! [[sinks]]
+//! type = "webhook"
+//! url = "https://example.com/hooks/grades"
+//! ```
+//!
+//! Command-line options and environment variables take precedence.
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use sustechcourse::notify::Sink;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub account: Account,
+    pub watch: Watch,
+    pub sinks: Vec<Sink>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Account {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub cas_login: Option<String>,
+    pub jsxsd: Option<String>,
+}
+
+/// Seconds of `Watcher::interval` and `Watcher::jitter`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Watch {
+    pub interval: Option<u64>,
+    pub jitter: Option<u64>,
+}
+
+/// Leaves out the password.
+impl std::fmt::Debug for Account {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Account")
+            .field("username", &self.username)
+            .field("cas_login", &self.cas_login)
+            .field("jsxsd", &self.jsxsd)
+            .finish()
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+        toml::from_str(&text).map_err(|err| format!("invalid {}: {}", path.display(), err))
+    }
+}
+
+#[test]
+fn test_config() {
+    let config: Config = toml::from_str(r#"
+[account]
+username = "11510000"
+password = "secret"
+
+[[sinks]]
+type = "command"
+program = "true"
+
+[[sinks]]
+type = "webhook"
+url = "https://example.com/hooks/secret"
+headers = { Authorization = "Bearer secret" }
+
+[[sinks]]
+type = "smtp"
+host = "127.0.0.1"
+port = 2525
+tls = "none"
+from = "bot@example.com"
+to = ["me@example.com"]
+"#).unwrap();
+    assert_eq!(config.account.password.as_deref(), Some("secret"));
+    assert!(!format!("{:?}", config).contains("secret"));
+    assert_eq!(config.watch.interval, None);
+    assert_eq!(config.sinks.len(), 3);
+    assert!(toml::from_str::<Config>("[account]\nuser = \"1\"").is_err());
+}
@@ -0,0 +1,428 @@
+//! Command-line client of the grade query, talking to jwxt directly.
+use chrono::NaiveDate;
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+use sustechcourse::{
+    blocking::{UserAgent, Watcher}, calendar::{self, Calendar}, credits, diff::Change,
+    export::{Column, Exporter, Format}, gpa::{Gpa, Report, Rules}, notify::Sink, ClassSession,
+    Course, Endpoints, Exam, Term,
+};
+use unicode_width::UnicodeWidthStr;
+
+mod config;
+
+use crate::config::Config;
+
+/// Seconds between polls of `watch` if not configured.
+const DEFAULT_WATCH_INTERVAL: u64 = 30 * 60;
+const DEFAULT_WATCH_JITTER: u64 = 5 * 60;
+
+#[derive(Parser)]
+#[command(name = "sustechcourse", version, about = "Query grades on SUSTech jwxt")]
+struct Args {
+    /// Student ID, asked for if not given
+    #[arg(short, long, env = "SUSTECH_USERNAME", global = true)]
+    username: Option<String>,
+    /// Password, prompted for if not given
+    #[arg(short, long, env = "SUSTECH_PASSWORD", hide_env_values = true, global = true)]
+    password: Option<String>,
+    /// Print JSON instead of a table
+    #[arg(long, global = true)]
+    json: bool,
+    /// CAS login page of another school
+    #[arg(long, env = "SUSTECH_CAS_LOGIN", requires = "jsxsd", global = true)]
+    cas_login: Option<String>,
+    /// Base URL of jsxsd of another school
+    #[arg(long, env = "SUSTECH_JSXSD", requires = "cas_login", global = true)]
+    jsxsd: Option<String>,
+    /// TOML file of the account and notification sinks
+    #[arg(long, env = "SUSTECH_CONFIG", global = true)]
+    config: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List grades of every course
+    Grades {
+        /// Only courses of the term, such as 2018-2019-1
+        #[arg(short, long, value_parser = parse_term)]
+        term: Option<Term>,
+    },
+    /// GPA of each term and overall
+    Gpa {
+        /// Count pass/fail courses, failed ones as zero points
+        #[arg(long)]
+        include_pass_fail: bool,
+    },
+    /// Credit hours by course type and category
+    Credits,
+    /// Save all courses as JSON, JSON Lines, CSV or Markdown
+    Export {
+        /// json, jsonl, csv or markdown
+        #[arg(short, long, default_value = "json", value_parser = parse_format)]
+        format: Format,
+        /// Columns to write, such as code,name,score
+        #[arg(short, long, value_delimiter = ',', value_parser = parse_column)]
+        columns: Vec<Column>,
+        /// Columns to sort by, such as term,code
+        #[arg(short, long, value_delimiter = ',', value_parser = parse_column)]
+        sort: Vec<Column>,
+        /// Sort in descending order
+        #[arg(long)]
+        desc: bool,
+        /// Output file instead of standard output
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Weekly classes of a term, or an iCalendar file of them
+    Timetable {
+        /// Term such as 2018-2019-1
+        #[arg(short, long, value_parser = parse_term)]
+        term: Term,
+        /// Write iCalendar, counting weeks from this date, such as 2018-09-10
+        #[arg(long)]
+        start: Option<NaiveDate>,
+        /// Output file of the iCalendar instead of standard output
+        #[arg(short, long, requires = "start")]
+        output: Option<PathBuf>,
+    },
+    /// Exam arrangements of a term, or an iCalendar file of them
+    Exams {
+        /// Term such as 2018-2019-1
+        #[arg(short, long, value_parser = parse_term)]
+        term: Term,
+        /// Write iCalendar
+        #[arg(long)]
+        ics: bool,
+        /// Output file of the iCalendar instead of standard output
+        #[arg(short, long, requires = "ics")]
+        output: Option<PathBuf>,
+    },
+    /// Poll grades, printing each change and sending it to the sinks of
+    /// the config, until interrupted
+    Watch {
+        /// Seconds between polls [default: 1800]
+        #[arg(long)]
+        interval: Option<u64>,
+        /// Add up to this many random seconds to each wait [default: 300]
+        #[arg(long)]
+        jitter: Option<u64>,
+    },
+}
+
+fn parse_term(text: &str) -> Result<Term, String> {
+    text.parse().map_err(|err| format!("{}", err))
+}
+
+fn parse_format(text: &str) -> Result<Format, String> {
+    text.parse().map_err(|err| format!("{}", err))
+}
+
+fn parse_column(text: &str) -> Result<Column, String> {
+    text.parse().map_err(|err| format!("{}", err))
+}
+
+fn credentials(args: &Args, config: &Config) -> io::Result<(String, String)> {
+    let username = match args.username.as_ref().or(config.account.username.as_ref()) {
+        Some(username) => username.clone(),
+        None => {
+            eprint!("Student ID: ");
+            io::stderr().flush()?;
+            let mut line = String::new();
+            io::stdin().lock().read_line(&mut line)?;
+            line.trim().to_string()
+        }
+    };
+    let password = match args.password.as_ref().or(config.account.password.as_ref()) {
+        Some(password) => password.clone(),
+        None => rpassword::prompt_password("Password: ")?,
+    };
+    Ok((username, password))
+}
+
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.width()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width());
+        }
+    }
+    let print_row = |cells: &[&str]| {
+        let line: Vec<String> = cells.iter().zip(&widths)
+            .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - cell.width())))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    };
+    print_row(headers);
+    for row in rows {
+        print_row(&row.iter().map(String::as_str).collect::<Vec<_>>());
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|err| err.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn format_gpa(gpa: &Gpa) -> String {
+    gpa.gpa.map(|gpa| format!("{:.2}", gpa)).unwrap_or_else(|| "-".into())
+}
+
+fn print_grades(courses: &[Course]) {
+    let rows: Vec<_> = courses.iter().map(|course| vec![
+        course.term.to_string(),
+        course.code.clone(),
+        course.name.clone(),
+        course.score.as_ref().map(ToString::to_string).unwrap_or_default(),
+        course.grade.clone(),
+        course.point.map(|point| point.to_string()).unwrap_or_default(),
+        course.hours.to_string(),
+    ]).collect();
+    print_table(&["Term", "Code", "Name", "Score", "Grade", "Point", "Credits"], &rows);
+}
+
+fn print_gpa(report: &Report) {
+    let rows: Vec<_> = report.by_term.iter().map(|(term, gpa)| vec![
+        term.to_string(),
+        format_gpa(gpa),
+        gpa.credits.to_string(),
+        format_gpa(&report.cumulative[term]),
+    ]).collect();
+    print_table(&["Term", "GPA", "Credits", "Cumulative"], &rows);
+    println!();
+    println!("Overall GPA {} over {} credits", format_gpa(&report.overall), report.overall.credits);
+}
+
+fn print_credits(summary: &credits::Summary) {
+    let row = |name: &str, credits: &credits::Credits| vec![
+        name.to_string(),
+        credits.earned.to_string(),
+        credits.failed.to_string(),
+        credits.withdrawn.to_string(),
+        credits.pending.to_string(),
+    ];
+    let headers = ["", "Earned", "Failed", "Withdrawn", "Pending"];
+    let mut rows: Vec<_> = summary.by_type.iter()
+        .map(|(name, credits)| row(name, credits))
+        .collect();
+    rows.push(row("Total", &summary.total));
+    print_table(&headers, &rows);
+    println!();
+    let rows: Vec<_> = summary.by_category.iter()
+        .map(|(name, credits)| row(name, credits))
+        .collect();
+    print_table(&headers, &rows);
+}
+
+/// Week numbers with consecutive ones joined, such as "1-8,10".
+fn format_weeks(weeks: &[u8]) -> String {
+    let mut runs: Vec<(u8, u8)> = Vec::new();
+    for &week in weeks {
+        match runs.last_mut() {
+            Some((_, end)) if *end + 1 == week => *end = week,
+            _ => runs.push((week, week)),
+        }
+    }
+    let runs: Vec<_> = runs.iter().map(|&(start, end)| match end - start {
+        0 => start.to_string(),
+        _ => format!("{}-{}", start, end),
+    }).collect();
+    runs.join(",")
+}
+
+fn print_timetable(sessions: &[ClassSession]) {
+    let mut sessions: Vec<_> = sessions.iter().collect();
+    sessions.sort_by_key(|session| (session.weekday.num_days_from_monday(), session.periods));
+    let rows: Vec<_> = sessions.iter().map(|session| vec![
+        session.weekday.to_string(),
+        format!("{}-{}", session.periods.0, session.periods.1),
+        format_weeks(&session.weeks),
+        session.code.clone(),
+        session.name.clone(),
+        session.teacher.clone(),
+        session.classroom.clone(),
+    ]).collect();
+    print_table(&["Day", "Periods", "Weeks", "Code", "Name", "Teacher", "Classroom"], &rows);
+}
+
+fn print_exams(exams: &[Exam]) {
+    let time = |exam: &Exam| match (exam.start, exam.end) {
+        (Some(start), Some(end)) =>
+            format!("{}-{}", start.format("%Y-%m-%d %H:%M"), end.format("%H:%M")),
+        _ => "-".into(),
+    };
+    let rows: Vec<_> = exams.iter().map(|exam| vec![
+        exam.code.clone(),
+        exam.name.clone(),
+        time(exam),
+        exam.room.clone(),
+        exam.seat.clone(),
+    ]).collect();
+    print_table(&["Code", "Name", "Time", "Room", "Seat"], &rows);
+}
+
+fn print_change(change: &Change) {
+    let course = change.course();
+    let score = |course: &Course| course.score.as_ref().map(ToString::to_string).unwrap_or_default();
+    let what = match change {
+        Change::Added(_) => format!("added, score {}", score(course)),
+        Change::Changed { old, .. } => format!("score {} -> {}", score(old), score(course)),
+        Change::Removed(_) => "removed".into(),
+    };
+    println!("{} {} {}: {}", course.term, course.code, course.name, what);
+}
+
+/// Send the change to every sink, reporting failures without stopping.
+fn notify(sinks: &[Sink], runtime: &tokio::runtime::Runtime, change: &Change) {
+    for sink in sinks {
+        if let Err(err) = runtime.block_on(sink.send(change)) {
+            eprintln!("error: {}", err);
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let config = match &args.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+    let mut agent = UserAgent::new();
+    let endpoints = match (&args.cas_login, &args.jsxsd) {
+        (Some(cas_login), Some(jsxsd)) => Some((cas_login, jsxsd)),
+        _ => config.account.cas_login.as_ref().zip(config.account.jsxsd.as_ref()),
+    };
+    if let Some((cas_login, jsxsd)) = endpoints {
+        let endpoints = Endpoints::new(cas_login, jsxsd).map_err(|err| err.to_string())?;
+        agent = sustechcourse::UserAgent::new().with_endpoints(endpoints).into();
+    }
+    let (username, password) = credentials(&args, &config).map_err(|err| err.to_string())?;
+    let mut agent = agent.login(username.clone(), password.clone())
+        .map_err(|err| err.to_string())?
+        .relogin_with((username, password));
+
+    match &args.command {
+        Command::Grades { term } => {
+            let courses = match term {
+                Some(term) => agent.query_course().and_then(|query| query.term(*term).send()),
+                None => agent.all_courses(),
+            }.map_err(|err| err.to_string())?;
+            if args.json {
+                print_json(&courses)?;
+            } else {
+                print_grades(&courses);
+            }
+        }
+        Command::Gpa { include_pass_fail } => {
+            let courses = agent.all_courses().map_err(|err| err.to_string())?;
+            let rules = Rules { exclude_pass_fail: !include_pass_fail, ..Rules::default() };
+            let report = rules.report(&courses);
+            if args.json {
+                print_json(&report)?;
+            } else {
+                print_gpa(&report);
+            }
+        }
+        Command::Credits => {
+            let courses = agent.all_courses().map_err(|err| err.to_string())?;
+            let summary = credits::summarize(&courses);
+            if args.json {
+                print_json(&summary)?;
+            } else {
+                print_credits(&summary);
+            }
+        }
+        Command::Export { format, columns, sort, desc, output } => {
+            let courses = agent.all_courses().map_err(|err| err.to_string())?;
+            let mut exporter = Exporter::new(*format).sort_by(sort.clone()).descending(*desc);
+            if !columns.is_empty() {
+                exporter = exporter.columns(columns.clone());
+            }
+            match output {
+                Some(path) => File::create(path)
+                    .map_err(|err| format!("cannot write {}: {}", path.display(), err))
+                    .and_then(|file| exporter.write(&courses, file).map_err(|err| err.to_string()))?,
+                None => exporter.write(&courses, io::stdout().lock())
+                    .map_err(|err| err.to_string())?,
+            }
+        }
+        Command::Timetable { term, start, output } => {
+            let sessions = agent.timetable(*term).map_err(|err| err.to_string())?;
+            match start {
+                Some(start) => {
+                    let calendar = Calendar::new(*start).name(&term.to_string());
+                    match output {
+                        Some(path) => File::create(path)
+                            .and_then(|file| calendar.write(&sessions, file))
+                            .map_err(|err| format!("cannot write {}: {}", path.display(), err))?,
+                        None => calendar.write(&sessions, io::stdout().lock())
+                            .map_err(|err| err.to_string())?,
+                    }
+                }
+                None if args.json => print_json(&sessions)?,
+                None => print_timetable(&sessions),
+            }
+        }
+        Command::Exams { term, ics, output } => {
+            let exams = agent.exams(*term).map_err(|err| err.to_string())?;
+            let name = format!("{} exams", term);
+            match output {
+                Some(path) => File::create(path)
+                    .and_then(|file| calendar::write_exams(&exams, Some(&name), file))
+                    .map_err(|err| format!("cannot write {}: {}", path.display(), err))?,
+                None if *ics => calendar::write_exams(&exams, Some(&name), io::stdout().lock())
+                    .map_err(|err| err.to_string())?,
+                None if args.json => print_json(&exams)?,
+                None => print_exams(&exams),
+            }
+        }
+        Command::Watch { interval, jitter } => {
+            let seconds = |arg: &Option<u64>, config: Option<u64>, default| {
+                Duration::from_secs(arg.or(config).unwrap_or(default))
+            };
+            let watcher = Watcher::new(agent)
+                .interval(seconds(interval, config.watch.interval, DEFAULT_WATCH_INTERVAL))
+                .jitter(seconds(jitter, config.watch.jitter, DEFAULT_WATCH_JITTER));
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(|err| err.to_string())?;
+            for changes in watcher {
+                // Keep watching through network or jwxt failures
+                let changes = match changes {
+                    Ok(changes) => changes,
+                    Err(err) => {
+                        eprintln!("error: {}", err);
+                        continue;
+                    }
+                };
+                for change in &changes {
+                    if args.json {
+                        let json = serde_json::to_string(change).map_err(|err| err.to_string())?;
+                        println!("{}", json);
+                    } else {
+                        print_change(change);
+                    }
+                    io::stdout().flush().map_err(|err| err.to_string())?;
+                    notify(&config.sinks, &runtime, change);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+    if let Err(err) = run(Args::parse()) {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,206 @@
+//! Synchronous versions of `UserAgent` and `LoginedAgent`, for scripts
+//! and command-line tools.
+//!
+//! Each agent drives its requests on its own single-threaded Tokio
+//! runtime, so none of them may be used from within an async context.
+//!
+//! ```no_run
+//! use sustechcourse::blocking::UserAgent;
+//!
+//! let mut agent = UserAgent::new().login("11510000".into(), "password".into()).unwrap();
+//! for course in agent.all_courses().unwrap() {
+//!     println!("{} {}", course.code, course.name);
+//! }
+//! ```
+use std::sync::Arc;
+#[cfg(feature = "watch")]
+use std::time::Duration;
+use tokio::runtime::{Builder, Runtime};
+
+#[cfg(feature = "watch")]
+use crate::diff::Change;
+use crate::{
+    ClassSession, CookieJar, Course, CourseError, Credentials, DisplayMode, Endpoints, Exam, Term,
+};
+
+#[derive(Debug, Clone)]
+pub struct UserAgent {
+    agent: crate::UserAgent,
+    runtime: Arc<Runtime>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LoginedAgent {
+    agent: crate::LoginedAgent,
+    runtime: Arc<Runtime>,
+}
+
+/// Iterates over changes of the grade list, blocking until each comes.
+/// The iterator never ends.
+#[cfg(feature = "watch")]
+#[derive(Debug)]
+pub struct Watcher {
+    watcher: crate::watch::Watcher,
+    runtime: Arc<Runtime>,
+}
+
+#[derive(Debug)]
+pub struct CourseQuery<'a> {
+    query: crate::CourseQuery<'a>,
+    runtime: &'a Runtime,
+}
+
+/// Panics if the runtime fails to start, see `UserAgent::try_from_async`.
+impl From<crate::UserAgent> for UserAgent {
+    fn from(agent: crate::UserAgent) -> Self {
+        UserAgent::try_from_async(agent).expect("fail to start tokio runtime")
+    }
+}
+
+impl Default for UserAgent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UserAgent {
+    /// Panics if the HTTP client fails to initialize, build an async
+    /// `UserAgent` with `UserAgentBuilder` and convert it with
+    /// `try_from_async` to get an error instead.
+    pub fn new() -> Self {
+        crate::UserAgent::new().into()
+    }
+
+    /// Wrap an async agent with a runtime of its own, failing with
+    /// `CourseError::Config` if the runtime cannot start.
+    pub fn try_from_async(agent: crate::UserAgent) -> Result<Self, CourseError> {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|err| CourseError::Config(format!("cannot start tokio runtime: {}", err)))?;
+        Ok(UserAgent { agent, runtime: Arc::new(runtime) })
+    }
+
+    pub fn endpoints(&self) -> &Endpoints {
+        self.agent.endpoints()
+    }
+
+    /// See `sustechcourse::UserAgent::restore`.
+    pub fn restore(self, cookies: CookieJar) -> Result<LoginedAgent, CourseError> {
+        let UserAgent { agent, runtime } = self;
+        let agent = runtime.block_on(agent.restore(cookies))?;
+        Ok(LoginedAgent { agent, runtime })
+    }
+
+    pub fn login(self, username: String, password: String) -> Result<LoginedAgent, CourseError> {
+        let UserAgent { agent, runtime } = self;
+        let agent = runtime.block_on(agent.login(username, password))?;
+        Ok(LoginedAgent { agent, runtime })
+    }
+}
+
+impl LoginedAgent {
+    pub fn endpoints(&self) -> &Endpoints {
+        self.agent.endpoints()
+    }
+
+    pub fn cookies(&self) -> &CookieJar {
+        self.agent.cookies()
+    }
+
+    /// See `sustechcourse::LoginedAgent::relogin_with`.
+    pub fn relogin_with<C: Credentials + 'static>(mut self, credentials: C) -> Self {
+        self.agent = self.agent.relogin_with(credentials);
+        self
+    }
+
+    /// The async agent of the same login.
+    pub fn into_async(self) -> crate::LoginedAgent {
+        self.agent
+    }
+
+    pub fn query_course(&self) -> Result<CourseQuery<'_>, CourseError> {
+        let query = self.runtime.block_on(self.agent.query_course())?;
+        Ok(CourseQuery { query, runtime: &self.runtime })
+    }
+
+    pub fn all_courses(&mut self) -> Result<Vec<Course>, CourseError> {
+        let runtime = self.runtime.clone();
+        runtime.block_on(self.agent.all_courses())
+    }
+
+    pub fn timetable(&self, term: Term) -> Result<Vec<ClassSession>, CourseError> {
+        self.runtime.block_on(self.agent.timetable(term))
+    }
+
+    pub fn exams(&self, term: Term) -> Result<Vec<Exam>, CourseError> {
+        self.runtime.block_on(self.agent.exams(term))
+    }
+}
+
+#[cfg(feature = "watch")]
+impl Watcher {
+    /// See `crate::watch::Watcher` for the defaults.
+    pub fn new(agent: LoginedAgent) -> Self {
+        let LoginedAgent { agent, runtime } = agent;
+        Watcher { watcher: crate::watch::Watcher::new(agent), runtime }
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.watcher = self.watcher.interval(interval);
+        self
+    }
+
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.watcher = self.watcher.jitter(jitter);
+        self
+    }
+
+    pub fn with_snapshot(mut self, courses: Vec<Course>) -> Self {
+        self.watcher = self.watcher.with_snapshot(courses);
+        self
+    }
+
+    pub fn snapshot(&self) -> Option<&[Course]> {
+        self.watcher.snapshot()
+    }
+}
+
+#[cfg(feature = "watch")]
+impl Iterator for Watcher {
+    type Item = Result<Vec<Change>, CourseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.runtime.block_on(self.watcher.next()))
+    }
+}
+
+impl CourseQuery<'_> {
+    pub fn years(&self) -> &[Term] {
+        self.query.years()
+    }
+
+    pub fn term(mut self, term: Term) -> Self {
+        self.query = self.query.term(term);
+        self
+    }
+
+    pub fn nature(mut self, nature: &str) -> Self {
+        self.query = self.query.nature(nature);
+        self
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.query = self.query.name(name);
+        self
+    }
+
+    pub fn display(mut self, mode: DisplayMode) -> Self {
+        self.query = self.query.display(mode);
+        self
+    }
+
+    pub fn send(&self) -> Result<Vec<Course>, CourseError> {
+        self.runtime.block_on(self.query.send())
+    }
+}
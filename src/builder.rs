@@ -0,0 +1,133 @@
+use reqwest::{
+    header::{HeaderMap, HeaderValue, USER_AGENT},
+    redirect, Certificate, Client, Proxy,
+};
+use std::time::Duration;
+
+use crate::{
+    error::CourseError,
+    session::{CookieJar, Session},
+    Endpoints, UserAgent,
+};
+
+const USER_AGENT_STRING: &str = "sustechcourse/0.1.0 (citric-acid.com.cn)";
+
+/// Configure the HTTP client of a `UserAgent`.
+///
+/// reqwest 0.11 has no read timeout, one between bytes of a response, so
+/// only `connect_timeout` and `timeout` are given; use `timeout` to bound
+/// a stalled read as well.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// use sustechcourse::{Proxy, UserAgent};
+///
+/// let agent = UserAgent::builder()
+///     .proxy(Proxy::all("socks5://127.0.0.1:1080").unwrap())
+///     .connect_timeout(Duration::from_secs(5))
+///     .timeout(Duration::from_secs(30))
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct UserAgentBuilder {
+    proxies: Vec<Proxy>,
+    no_proxy: bool,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    root_certificates: Vec<Certificate>,
+    user_agent: Option<String>,
+    cookies: Option<CookieJar>,
+    endpoints: Option<Endpoints>,
+}
+
+impl UserAgentBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send requests through a SOCKS or HTTP proxy, instead of the ones
+    /// set in environment variables.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    /// Ignore proxies set in environment variables.
+    pub fn no_proxy(mut self) -> Self {
+        self.no_proxy = true;
+        self
+    }
+
+    /// Timeout of connecting, including the TLS handshake.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout of each request, from connecting until the response body
+    /// is read. This is also what stops a read that stalls, in place of a
+    /// read timeout.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Trust an extra CA, such as the one of a TLS-intercepting proxy.
+    pub fn add_root_certificate(mut self, cert: Certificate) -> Self {
+        self.root_certificates.push(cert);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Keep cookies in the given jar, which may be shared with others.
+    pub fn cookie_jar(mut self, cookies: CookieJar) -> Self {
+        self.cookies = Some(cookies);
+        self
+    }
+
+    pub fn endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.endpoints = Some(endpoints);
+        self
+    }
+
+    pub fn build(self) -> Result<UserAgent, CourseError> {
+        let user_agent = self.user_agent.as_ref().map_or(USER_AGENT_STRING, String::as_str);
+        let user_agent = HeaderValue::from_str(user_agent)
+            .map_err(|_| CourseError::Config(format!("invalid user agent {:?}", user_agent)))?;
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, user_agent);
+
+        // Redirects and cookies are handled by `Session`
+        let mut builder = Client::builder()
+            .gzip(true)
+            .redirect(redirect::Policy::none())
+            .default_headers(headers);
+        // Proxies in environment variables are used unless any is given
+        if self.no_proxy {
+            builder = builder.no_proxy();
+        }
+        for proxy in self.proxies {
+            builder = builder.proxy(proxy);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        for cert in self.root_certificates {
+            builder = builder.add_root_certificate(cert);
+        }
+        let client = builder.build()?;
+
+        Ok(UserAgent {
+            session: Session::new(client, self.cookies.unwrap_or_default()),
+            endpoints: self.endpoints.unwrap_or_default(),
+        })
+    }
+}
@@ -0,0 +1,353 @@
+//! iCalendar (RFC 5545) export of the timetable, one weekly recurring
+//! event per class, and of exams, in the time zone of Asia/Shanghai.
+//!
+//! ```
+//! # let sessions: Vec<sustechcourse::ClassSession> = vec![];
+//! use chrono::NaiveDate;
+//! use sustechcourse::calendar::Calendar;
+//!
+//! let ics = Calendar::new(NaiveDate::from_ymd_opt(2018, 9, 10).unwrap())
+//!     .name("2018-2019-1")
+//!     .to_string(&sessions);
+//! ```
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+use log::warn;
+use std::io::{self, Write};
+
+use crate::{ClassSession, Exam};
+
+pub const TIMEZONE: &str = "Asia/Shanghai";
+
+/// Start and end of each period at SUSTech, from period 1.
+const SUSTECH_PERIODS: &[(&str, &str)] = &[
+    ("08:00", "08:50"),
+    ("09:00", "09:50"),
+    ("10:20", "11:10"),
+    ("11:20", "12:10"),
+    ("14:00", "14:50"),
+    ("15:00", "15:50"),
+    ("16:20", "17:10"),
+    ("17:20", "18:10"),
+    ("19:00", "19:50"),
+    ("20:00", "20:50"),
+    ("21:00", "21:50"),
+];
+
+/// Lines longer than this many octets are folded.
+const MAX_LINE: usize = 75;
+
+/// Places classes of the timetable on dates of a term.
+#[derive(Debug, Clone)]
+pub struct Calendar {
+    /// Monday of week 1.
+    term_start: NaiveDate,
+    periods: Vec<(NaiveTime, NaiveTime)>,
+    name: Option<String>,
+}
+
+/// A `VEVENT`, with times local to `TIMEZONE`.
+#[derive(Debug, Clone)]
+pub(crate) struct Event {
+    pub uid: String,
+    pub summary: String,
+    pub location: String,
+    pub description: String,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    /// Such as "FREQ=WEEKLY;COUNT=16".
+    pub rrule: Option<String>,
+    /// Start of occurrences left out of `rrule`.
+    pub exdates: Vec<NaiveDateTime>,
+}
+
+impl Calendar {
+    /// Weeks are counted from the one containing `term_start`, usually
+    /// the Monday of week 1. Periods default to the times of SUSTech.
+    pub fn new(term_start: NaiveDate) -> Self {
+        let days = term_start.weekday().num_days_from_monday();
+        let time = |text| NaiveTime::parse_from_str(text, "%H:%M").expect("invalid period time");
+        let periods = SUSTECH_PERIODS.iter()
+            .map(|&(start, end)| (time(start), time(end)))
+            .collect();
+        Calendar {
+            term_start: term_start - Duration::days(days.into()),
+            periods,
+            name: None,
+        }
+    }
+
+    /// Start and end of each period, from period 1.
+    pub fn periods(mut self, periods: Vec<(NaiveTime, NaiveTime)>) -> Self {
+        self.periods = periods;
+        self
+    }
+
+    /// Shown by calendar apps as the name of a subscription.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// The date of a weekday in a teaching week, counted from 1.
+    pub fn date(&self, week: u8, weekday: Weekday) -> NaiveDate {
+        let days = (i64::from(week) - 1) * 7 + i64::from(weekday.num_days_from_monday());
+        self.term_start + Duration::days(days)
+    }
+
+    /// Classes outside of the known periods are left out.
+    pub fn write<W: Write>(&self, sessions: &[ClassSession], writer: W) -> io::Result<()> {
+        let events: Vec<Event> = sessions.iter()
+            .filter_map(|session| self.event(session))
+            .collect();
+        write_calendar(self.name.as_deref(), &events, writer)
+    }
+
+    pub fn to_string(&self, sessions: &[ClassSession]) -> String {
+        let mut buf = Vec::new();
+        self.write(sessions, &mut buf).expect("write to memory");
+        String::from_utf8(buf).expect("valid UTF-8")
+    }
+
+    fn event(&self, session: &ClassSession) -> Option<Event> {
+        let (first, last) = session.periods;
+        let times = first.checked_sub(1)
+            .and_then(|first| self.periods.get(usize::from(first)))
+            .zip(last.checked_sub(1).and_then(|last| self.periods.get(usize::from(last))));
+        let (start, end) = match (times, session.weeks.first()) {
+            (Some(((start, _), (_, end))), Some(&week)) => {
+                let date = self.date(week, session.weekday);
+                (date.and_time(*start), date.and_time(*end))
+            }
+            _ => {
+                warn!("class {} at periods {:?} left out of calendar", session.name, session.periods);
+                return None;
+            }
+        };
+        let (rrule, exdates) = recurrence(&session.weeks);
+        let description = [session.code.as_str(), session.teacher.as_str()].iter()
+            .filter(|text| !text.is_empty())
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let id = if session.code.is_empty() { &session.name } else { &session.code };
+        Some(Event {
+            uid: format!("{}-{}@sustechcourse", start.format("%Y%m%dT%H%M"), id),
+            summary: session.name.clone(),
+            location: session.classroom.clone(),
+            description,
+            start,
+            end,
+            rrule,
+            exdates: exdates.into_iter()
+                .map(|week| start + Duration::weeks(i64::from(week - session.weeks[0])))
+                .collect(),
+        })
+    }
+}
+
+/// Write exams as single events, leaving out ones not yet arranged.
+pub fn write_exams<W: Write>(exams: &[Exam], name: Option<&str>, writer: W) -> io::Result<()> {
+    let events: Vec<Event> = exams.iter().filter_map(|exam| {
+        let (start, end) = (exam.start?, exam.end?);
+        let description = match exam.seat.as_str() {
+            "" => exam.code.clone(),
+            seat => format!("{} seat {}", exam.code, seat).trim_start().to_string(),
+        };
+        let id = if exam.code.is_empty() { &exam.name } else { &exam.code };
+        Some(Event {
+            uid: format!("exam-{}-{}@sustechcourse", start.format("%Y%m%dT%H%M"), id),
+            summary: format!("Exam: {}", exam.name),
+            location: exam.room.clone(),
+            description,
+            start,
+            end,
+            rrule: None,
+            exdates: Vec::new(),
+        })
+    }).collect();
+    write_calendar(name, &events, writer)
+}
+
+pub fn exams_to_string(exams: &[Exam], name: Option<&str>) -> String {
+    let mut buf = Vec::new();
+    write_exams(exams, name, &mut buf).expect("write to memory");
+    String::from_utf8(buf).expect("valid UTF-8")
+}
+
+/// The recurrence rule of a sorted week list and the weeks it has to
+/// leave out: every n weeks if evenly spaced, such as odd weeks, or
+/// else every week from first to last except the missing ones.
+fn recurrence(weeks: &[u8]) -> (Option<String>, Vec<u8>) {
+    let (first, last) = match (weeks.first(), weeks.last()) {
+        (Some(&first), Some(&last)) if weeks.len() > 1 => (first, last),
+        _ => return (None, Vec::new()),
+    };
+    let interval = weeks[1] - weeks[0];
+    if weeks.windows(2).all(|pair| pair[1] - pair[0] == interval) {
+        let rrule = match interval {
+            1 => format!("FREQ=WEEKLY;COUNT={}", weeks.len()),
+            _ => format!("FREQ=WEEKLY;INTERVAL={};COUNT={}", interval, weeks.len()),
+        };
+        (Some(rrule), Vec::new())
+    } else {
+        let missing = (first..=last).filter(|week| !weeks.contains(week)).collect();
+        (Some(format!("FREQ=WEEKLY;COUNT={}", last - first + 1)), missing)
+    }
+}
+
+/// Write a `VCALENDAR` of the events, with CRLF line breaks and long
+/// lines folded.
+pub(crate) fn write_calendar<W: Write>(name: Option<&str>, events: &[Event], mut writer: W)
+    -> io::Result<()>
+{
+    let mut line = |name: &str, value: &str| -> io::Result<()> {
+        write_folded(&mut writer, &format!("{}:{}", name, value))
+    };
+    let local = |time: &NaiveDateTime| time.format("%Y%m%dT%H%M%S").to_string();
+    let tzid = |name: &str| format!("{};TZID={}", name, TIMEZONE);
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+    line("BEGIN", "VCALENDAR")?;
+    line("VERSION", "2.0")?;
+    line("PRODID", &format!("-//sustechcourse//sustechcourse {}//EN", env!("CARGO_PKG_VERSION")))?;
+    line("CALSCALE", "GREGORIAN")?;
+    line("METHOD", "PUBLISH")?;
+    if let Some(name) = name {
+        line("X-WR-CALNAME", &escape(name))?;
+    }
+    line("X-WR-TIMEZONE", TIMEZONE)?;
+    // China has no daylight saving time since 1991
+    line("BEGIN", "VTIMEZONE")?;
+    line("TZID", TIMEZONE)?;
+    line("BEGIN", "STANDARD")?;
+    line("DTSTART", "19700101T000000")?;
+    line("TZOFFSETFROM", "+0800")?;
+    line("TZOFFSETTO", "+0800")?;
+    line("TZNAME", "CST")?;
+    line("END", "STANDARD")?;
+    line("END", "VTIMEZONE")?;
+    for event in events {
+        line("BEGIN", "VEVENT")?;
+        line("UID", &escape(&event.uid))?;
+        line("DTSTAMP", &stamp)?;
+        line(&tzid("DTSTART"), &local(&event.start))?;
+        line(&tzid("DTEND"), &local(&event.end))?;
+        if let Some(rrule) = &event.rrule {
+            line("RRULE", rrule)?;
+        }
+        if !event.exdates.is_empty() {
+            let dates: Vec<_> = event.exdates.iter().map(local).collect();
+            line(&tzid("EXDATE"), &dates.join(","))?;
+        }
+        line("SUMMARY", &escape(&event.summary))?;
+        if !event.location.is_empty() {
+            line("LOCATION", &escape(&event.location))?;
+        }
+        if !event.description.is_empty() {
+            line("DESCRIPTION", &escape(&event.description))?;
+        }
+        line("END", "VEVENT")?;
+    }
+    line("END", "VCALENDAR")
+}
+
+/// Escape a TEXT value.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => (),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Write a content line, breaking it before `MAX_LINE` octets without
+/// splitting a UTF-8 character; continuations start with a space.
+fn write_folded<W: Write>(writer: &mut W, line: &str) -> io::Result<()> {
+    let mut rest = line;
+    let mut limit = MAX_LINE;
+    while rest.len() > limit {
+        let mut at = limit;
+        while !rest.is_char_boundary(at) {
+            at -= 1;
+        }
+        writer.write_all(&rest.as_bytes()[..at])?;
+        writer.write_all(b"\r\n ")?;
+        rest = &rest[at..];
+        limit = MAX_LINE - 1;
+    }
+    writer.write_all(rest.as_bytes())?;
+    writer.write_all(b"\r\n")
+}
+
+#[test]
+fn test_recurrence() {
+    assert_eq!(recurrence(&[3]), (None, vec![]));
+    assert_eq!(recurrence(&[1, 2, 3, 4]), (Some("FREQ=WEEKLY;COUNT=4".into()), vec![]));
+    assert_eq!(recurrence(&[1, 3, 5]), (Some("FREQ=WEEKLY;INTERVAL=2;COUNT=3".into()), vec![]));
+    assert_eq!(recurrence(&[1, 2, 5]), (Some("FREQ=WEEKLY;COUNT=5".into()), vec![3, 4]));
+}
+
+#[test]
+fn test_calendar() {
+    let session = ClassSession {
+        code: "CS102A".into(),
+        name: "计算机程序设计基础A".into(),
+        teacher: "张三".into(),
+        classroom: "一教105, 讲堂".into(),
+        weekday: Weekday::Wed,
+        periods: (3, 4),
+        weeks: vec![1, 2, 3, 5],
+    };
+    // A Sunday, counted as in the week of Monday 2018-09-03
+    let calendar = Calendar::new(NaiveDate::from_ymd_opt(2018, 9, 9).unwrap());
+    assert_eq!(calendar.date(2, Weekday::Mon), NaiveDate::from_ymd_opt(2018, 9, 10).unwrap());
+    let ics = calendar.name("2018-2019-1").to_string(&[session]);
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    assert!(ics.lines().all(|line| line.len() <= MAX_LINE + 1));
+    let lines: Vec<_> = ics.lines().map(|line| line.trim_end_matches('\r')).collect();
+    assert!(lines.contains(&"DTSTART;TZID=Asia/Shanghai:20180905T102000"));
+    assert!(lines.contains(&"DTEND;TZID=Asia/Shanghai:20180905T121000"));
+    assert!(lines.contains(&"RRULE:FREQ=WEEKLY;COUNT=5"));
+    assert!(lines.contains(&"EXDATE;TZID=Asia/Shanghai:20180926T102000"));
+    assert!(lines.contains(&"LOCATION:一教105\\, 讲堂"));
+    assert!(lines.contains(&"DESCRIPTION:CS102A 张三"));
+}
+
+#[test]
+fn test_exams() {
+    let at = |day, hour| NaiveDate::from_ymd_opt(2019, 1, day).unwrap().and_hms_opt(hour, 0, 0);
+    let exam = |code: &str, start, end| Exam {
+        code: code.into(),
+        name: "课程".into(),
+        start,
+        end,
+        room: "一教105".into(),
+        seat: "23".into(),
+    };
+    let exams = [exam("CS102A", at(10, 9), at(10, 11)), exam("MA101B", None, None)];
+    let ics = exams_to_string(&exams, Some("Exams"));
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+    assert!(ics.contains("DTSTART;TZID=Asia/Shanghai:20190110T090000\r\n"));
+    assert!(ics.contains("SUMMARY:Exam: 课程\r\n"));
+    assert!(ics.contains("DESCRIPTION:CS102A seat 23\r\n"));
+    assert!(!ics.contains("RRULE"));
+}
+
+#[test]
+fn test_fold() {
+    let mut buf = Vec::new();
+    let line = format!("SUMMARY:{}", "课".repeat(40));
+    write_folded(&mut buf, &line).unwrap();
+    let text = String::from_utf8(buf).unwrap();
+    assert!(text.split("\r\n").all(|part| part.len() <= MAX_LINE));
+    assert_eq!(text.replace("\r\n ", ""), format!("{}\r\n", line));
+}
@@ -0,0 +1,44 @@
+//! futures 0.1 versions of the async API, for callers not yet moved to
+//! std futures. They will be removed in a later release.
+//!
+//! The HTTP client runs on Tokio 1, so these futures must be polled
+//! within the context of a Tokio 1 runtime, e.g. after
+//! `tokio::runtime::Runtime::enter`.
+use futures::TryFutureExt;
+use futures01::Future;
+
+use crate::{CookieJar, Course, CourseError, CourseQuery, LoginedAgent, UserAgent};
+
+impl UserAgent {
+    pub fn login_compat(self, username: String, password: String)
+        -> impl Future<Item = LoginedAgent, Error = CourseError>
+    {
+        Box::pin(self.login(username, password)).compat()
+    }
+
+    pub fn restore_compat(self, cookies: CookieJar)
+        -> impl Future<Item = LoginedAgent, Error = CourseError>
+    {
+        Box::pin(self.restore(cookies)).compat()
+    }
+}
+
+impl LoginedAgent {
+    pub fn query_course_compat(&self)
+        -> impl Future<Item = CourseQuery<'_>, Error = CourseError> + '_
+    {
+        Box::pin(self.query_course()).compat()
+    }
+
+    pub fn all_courses_compat(&mut self)
+        -> impl Future<Item = Vec<Course>, Error = CourseError> + '_
+    {
+        Box::pin(self.all_courses()).compat()
+    }
+}
+
+impl CourseQuery<'_> {
+    pub fn send_compat(&self) -> impl Future<Item = Vec<Course>, Error = CourseError> + '_ {
+        Box::pin(self.send()).compat()
+    }
+}
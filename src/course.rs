@@ -0,0 +1,417 @@
+use log::warn;
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::{CourseError, Term};
+
+/// One row of the grade table.
+///
+/// Every field serializes to the text of its cell, so the JSON is the
+/// same as when every field was a plain string: a point of "4.0" stays
+/// "4.0" and a score of "通过" stays "通过". A typed field that no longer
+/// matches its cell in `raw` serializes to the text of its value.
+#[derive(Debug, Clone)]
+pub struct Course {
+    pub code: String,
+    pub term: Term,
+    pub name: String,
+    pub grade: String,
+    pub score: Option<Score>,
+    pub point: Option<f32>,
+    pub hours: f32,
+    pub eval_method: EvalMethod,
+    pub course_type: String,
+    pub category: String,
+    /// Cells of columns not known to this crate, by their header.
+    pub extra: BTreeMap<String, String>,
+    /// Text of every cell in the row, as shown on jwxt.
+    pub raw: Vec<String>,
+    cells: CellIndex,
+}
+
+/// Positions in `Course::raw` of the cells of typed fields.
+#[derive(Debug, Clone, Copy, Default)]
+struct CellIndex {
+    score: Option<usize>,
+    point: Option<usize>,
+    hours: Option<usize>,
+    eval_method: Option<usize>,
+}
+
+/// Score of a course, as written in the grade table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Score {
+    /// Hundred-mark score, such as 85 or 92.5.
+    Numeric(f32),
+    /// Letter or level grade, such as "A-" or "优秀".
+    Letter(String),
+    Pass,
+    Fail,
+    /// Absent from the exam (缺考).
+    Absent,
+    /// Exam deferred to a later date (缓考).
+    Deferred,
+    /// Withdrawn from the course (退课).
+    Withdrawn,
+}
+
+/// How a course is evaluated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalMethod {
+    /// Final exam (考试).
+    Exam,
+    /// Coursework assessment (考查).
+    Assessment,
+    Other(String),
+}
+
+/// A known column of the grade table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    /// Row number (序号), not kept.
+    Id,
+    Term,
+    Code,
+    Name,
+    Grade,
+    Score,
+    Point,
+    Hours,
+    EvalMethod,
+    CourseType,
+    Category,
+}
+
+/// Header texts of each known column, including the ones used by other
+/// versions of jwxt.
+const HEADERS: &[(&str, Field)] = &[
+    ("序号", Field::Id),
+    ("开课学期", Field::Term),
+    ("学年学期", Field::Term),
+    ("课程编号", Field::Code),
+    ("课程号", Field::Code),
+    ("课程名称", Field::Name),
+    ("等级成绩", Field::Grade),
+    ("成绩", Field::Score),
+    ("总成绩", Field::Score),
+    ("总评成绩", Field::Score),
+    ("绩点", Field::Point),
+    ("学分", Field::Hours),
+    ("考核方式", Field::EvalMethod),
+    ("课程属性", Field::CourseType),
+    ("课程性质", Field::Category),
+];
+
+/// Columns a course cannot be built without.
+const REQUIRED: &[Field] = &[Field::Term, Field::Code];
+
+/// Columns expected on the table, in their usual order after the id column.
+const POSITIONAL: &[Field] = &[
+    Field::Term, Field::Code, Field::Name, Field::Grade, Field::Score,
+    Field::Point, Field::Hours, Field::EvalMethod, Field::CourseType, Field::Category,
+];
+
+/// Which field each column of the grade table holds, read from its
+/// header row.
+#[derive(Debug, Clone)]
+pub(crate) struct Columns {
+    headers: Vec<String>,
+    fields: Vec<Option<Field>>,
+}
+
+impl Columns {
+    /// Map columns by the text of their headers. Fails if the term or
+    /// code column is missing, and warns about other missing ones.
+    pub fn new(headers: Vec<String>) -> Result<Self, CourseError> {
+        let headers: Vec<String> = headers.iter().map(|header| header.trim().to_string()).collect();
+        let fields: Vec<_> = headers.iter()
+            .map(|header| HEADERS.iter().find(|(text, _)| text == header).map(|&(_, field)| field))
+            .collect();
+        let missing = |field: &&Field| !fields.contains(&Some(**field));
+        let required: Vec<_> = REQUIRED.iter().filter(missing).collect();
+        if !required.is_empty() {
+            let message = format!("missing columns {:?} in headers {:?}", required, headers);
+            return Err(CourseError::Parse { page: "course list", message });
+        }
+        let optional: Vec<_> = POSITIONAL.iter().filter(missing).collect();
+        if !optional.is_empty() {
+            warn!("grade table changed, missing columns {:?} in headers {:?}", optional, headers);
+        }
+        Ok(Columns { headers, fields })
+    }
+
+    /// Build a course from the text of each cell in a row.
+    pub fn course(&self, cells: Vec<String>) -> Option<Course> {
+        let index = |field: Field| self.fields.iter().position(|&f| f == Some(field));
+        let cell = |field: Field| {
+            index(field).and_then(|i| cells.get(i)).map(|s| s.trim()).unwrap_or_default()
+        };
+        let extra = self.headers.iter().zip(&self.fields).zip(&cells)
+            .filter(|((header, field), _)| field.is_none() && !header.is_empty())
+            .map(|((header, _), cell)| (header.clone(), cell.trim().to_string()))
+            .collect();
+        Some(Course {
+            term: cell(Field::Term).parse().ok()?,
+            code: cell(Field::Code).to_string(),
+            name: cell(Field::Name).to_string(),
+            grade: cell(Field::Grade).to_string(),
+            score: cell(Field::Score).parse().ok(),
+            point: parse_number(cell(Field::Point)),
+            hours: parse_number(cell(Field::Hours)).unwrap_or_default(),
+            eval_method: cell(Field::EvalMethod).parse().unwrap(),
+            course_type: cell(Field::CourseType).to_string(),
+            category: cell(Field::Category).to_string(),
+            extra,
+            cells: CellIndex {
+                score: index(Field::Score),
+                point: index(Field::Point),
+                hours: index(Field::Hours),
+                eval_method: index(Field::EvalMethod),
+            },
+            raw: cells,
+        })
+    }
+}
+
+impl Course {
+    /// A course not read from the grade table, with other fields empty.
+    pub fn new(term: Term, code: &str, name: &str) -> Self {
+        Course {
+            code: code.to_string(),
+            term,
+            name: name.to_string(),
+            grade: String::new(),
+            score: None,
+            point: None,
+            hours: 0.0,
+            eval_method: EvalMethod::Other(String::new()),
+            course_type: String::new(),
+            category: String::new(),
+            extra: BTreeMap::new(),
+            raw: Vec::new(),
+            cells: CellIndex::default(),
+        }
+    }
+
+    /// Text of the score cell, or of `score` if it does not match the cell.
+    pub fn score_text(&self) -> String {
+        match self.cell(self.cells.score) {
+            Some(text) if text.parse().ok() == self.score => text.to_string(),
+            _ => self.score.as_ref().map(ToString::to_string).unwrap_or_default(),
+        }
+    }
+
+    /// Text of the point cell, or of `point` if it does not match the cell.
+    pub fn point_text(&self) -> String {
+        match self.cell(self.cells.point) {
+            Some(text) if parse_number(text) == self.point => text.to_string(),
+            _ => self.point.map(|point| point.to_string()).unwrap_or_default(),
+        }
+    }
+
+    /// Text of the hours cell, or of `hours` if it does not match the cell.
+    pub fn hours_text(&self) -> String {
+        match self.cell(self.cells.hours) {
+            Some(text) if parse_number(text).unwrap_or_default() == self.hours => text.to_string(),
+            _ => self.hours.to_string(),
+        }
+    }
+
+    /// Text of the evaluation method cell, or of `eval_method` if it does
+    /// not match the cell.
+    pub fn eval_method_text(&self) -> String {
+        match self.cell(self.cells.eval_method) {
+            Some(text) if text.parse().ok() == Some(self.eval_method.clone()) => text.to_string(),
+            _ => self.eval_method.to_string(),
+        }
+    }
+
+    fn cell(&self, index: Option<usize>) -> Option<&str> {
+        index.and_then(|i| self.raw.get(i)).map(|text| text.trim())
+    }
+
+    /// Build from the text of each column, in the usual table order
+    /// without the id column.
+    #[cfg(test)]
+    pub(crate) fn from_cells(cells: &[&str]) -> Option<Course> {
+        if cells.len() < 2 {
+            return None;
+        }
+        let columns = Columns {
+            headers: vec![String::new(); POSITIONAL.len()],
+            fields: POSITIONAL.iter().cloned().map(Some).collect(),
+        };
+        columns.course(cells.iter().map(|cell| cell.to_string()).collect())
+    }
+}
+
+/// A finite number, or `None` for an empty cell, "NaN" or "inf".
+fn parse_number(text: &str) -> Option<f32> {
+    text.parse().ok().filter(|value: &f32| value.is_finite())
+}
+
+impl FromStr for Score {
+    type Err = ();
+
+    /// Parse a non-empty score cell. Unrecognized text is kept as a letter.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(());
+        }
+        if let Some(value) = parse_number(s) {
+            return Ok(Score::Numeric(value));
+        }
+        Ok(match s {
+            "P" | "PASS" | "Pass" | "通过" | "合格" => Score::Pass,
+            "NP" | "FAIL" | "Fail" | "不通过" | "不合格" => Score::Fail,
+            "缺考" => Score::Absent,
+            "缓考" => Score::Deferred,
+            "W" | "退课" => Score::Withdrawn,
+            _ => Score::Letter(s.to_string()),
+        })
+    }
+}
+
+impl Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Score::Numeric(value) => write!(f, "{}", value),
+            Score::Letter(letter) => write!(f, "{}", letter),
+            Score::Pass => write!(f, "P"),
+            Score::Fail => write!(f, "NP"),
+            Score::Absent => write!(f, "缺考"),
+            Score::Deferred => write!(f, "缓考"),
+            Score::Withdrawn => write!(f, "W"),
+        }
+    }
+}
+
+impl FromStr for EvalMethod {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.trim() {
+            "考试" => EvalMethod::Exam,
+            "考查" => EvalMethod::Assessment,
+            other => EvalMethod::Other(other.to_string()),
+        })
+    }
+}
+
+impl Display for EvalMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvalMethod::Exam => write!(f, "考试"),
+            EvalMethod::Assessment => write!(f, "考查"),
+            EvalMethod::Other(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+impl Serialize for Course {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Course", 11)?;
+        state.serialize_field("code", &self.code)?;
+        state.serialize_field("term", &self.term)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("grade", &self.grade)?;
+        state.serialize_field("score", &self.score_text())?;
+        state.serialize_field("point", &self.point_text())?;
+        state.serialize_field("hours", &self.hours_text())?;
+        state.serialize_field("eval_method", &self.eval_method_text())?;
+        state.serialize_field("course_type", &self.course_type)?;
+        state.serialize_field("category", &self.category)?;
+        if self.extra.is_empty() {
+            state.skip_field("extra")?;
+        } else {
+            state.serialize_field("extra", &self.extra)?;
+        }
+        state.end()
+    }
+}
+
+#[test]
+fn test_parse_score() {
+    assert_eq!("92.5".parse(), Ok(Score::Numeric(92.5)));
+    assert_eq!("A-".parse(), Ok(Score::Letter("A-".into())));
+    assert_eq!("通过".parse(), Ok(Score::Pass));
+    assert_eq!("NP".parse(), Ok(Score::Fail));
+    assert_eq!("缺考".parse(), Ok(Score::Absent));
+    assert_eq!("缓考".parse(), Ok(Score::Deferred));
+    assert_eq!("退课".parse(), Ok(Score::Withdrawn));
+    assert_eq!("NaN".parse(), Ok(Score::Letter("NaN".into())));
+    assert_eq!("inf".parse(), Ok(Score::Letter("inf".into())));
+    assert_eq!("".parse::<Score>(), Err(()));
+}
+
+#[test]
+fn test_course_from_cells() {
+    let cells = ["2018-2019-1", "CS101", "Intro", "A", "93", "4.0", "3", "考试", "必修", "专业核心"];
+    let course = Course::from_cells(&cells).unwrap();
+    assert_eq!(course.term, "2018-2019-1".parse().unwrap());
+    assert_eq!(course.score, Some(Score::Numeric(93.0)));
+    assert_eq!(course.point, Some(4.0));
+    assert_eq!(course.hours, 3.0);
+    assert_eq!(course.eval_method, EvalMethod::Exam);
+    assert_eq!(course.raw.len(), 10);
+
+    let cells = ["2018-2019-1", "PE101", "PE", "", "P", "", "", "考查"];
+    let course = Course::from_cells(&cells).unwrap();
+    assert_eq!(course.score, Some(Score::Pass));
+    assert_eq!(course.point, None);
+    assert_eq!(course.hours, 0.0);
+    assert_eq!(course.category, "");
+
+    let cells = ["2018-2019-1", "CS102", "Java", "", "通过", "4.0", "NaN", "退课"];
+    let course = Course::from_cells(&cells).unwrap();
+    assert_eq!(course.hours, 0.0);
+    let json = serde_json::to_value(&course).unwrap();
+    assert_eq!(json["score"], "通过");
+    assert_eq!(json["point"], "4.0");
+    assert_eq!(json["hours"], "NaN");
+    assert_eq!(json["eval_method"], "退课");
+    assert!(json.get("extra").is_none());
+
+    let mut course = course;
+    course.score = Some(Score::Numeric(93.0));
+    course.hours = 2.5;
+    let json = serde_json::to_value(&course).unwrap();
+    assert_eq!(json["score"], "93");
+    assert_eq!(json["point"], "4.0");
+    assert_eq!(json["hours"], "2.5");
+    course.raw[5] = "3.7".into();
+    assert_eq!(course.point_text(), "4");
+
+    let course = Course { point: Some(3.3), ..Course::new(course.term, "CS102", "Java") };
+    let json = serde_json::to_value(&course).unwrap();
+    assert_eq!(json["point"], "3.3");
+    assert_eq!(json["score"], "");
+    assert_eq!(json["hours"], "0");
+
+    let cells = ["学期", "课程编号"];
+    assert!(Course::from_cells(&cells).is_none());
+}
+
+#[test]
+fn test_columns() {
+    let strings = |cells: &[&str]| cells.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    let headers = ["序号", "课程编号", "开课学期", " 成绩 ", "补考标记", "学分"];
+    let columns = Columns::new(strings(&headers)).unwrap();
+    let course = columns.course(strings(&["1", "CS101", "2018-2019-1", "93", "否", "3"])).unwrap();
+    assert_eq!(course.code, "CS101");
+    assert_eq!(course.term, "2018-2019-1".parse().unwrap());
+    assert_eq!(course.score, Some(Score::Numeric(93.0)));
+    assert_eq!(course.hours, 3.0);
+    assert_eq!(course.name, "");
+    assert_eq!(course.extra.len(), 1);
+    assert_eq!(course.extra["补考标记"], "否");
+    assert_eq!(course.raw.len(), 6);
+
+    match Columns::new(strings(&["序号", "课程名称", "成绩"])) {
+        Err(CourseError::Parse { message, .. }) => assert!(message.contains("Term")),
+        other => panic!("unexpected {:?}", other),
+    }
+}
@@ -0,0 +1,97 @@
+//! Credit totals by course type and category.
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::{Course, Score};
+
+/// Outcome of a course as far as credits are concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Status {
+    Earned,
+    Failed,
+    Withdrawn,
+    /// Not graded yet or exam deferred.
+    Pending,
+}
+
+/// Credit hours of courses, split by their status.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Credits {
+    pub earned: f32,
+    pub failed: f32,
+    pub withdrawn: f32,
+    pub pending: f32,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Summary {
+    pub total: Credits,
+    /// Keyed by `Course::course_type`, such as "必修" (compulsory).
+    pub by_type: BTreeMap<String, Credits>,
+    /// Keyed by `Course::category`, such as "通识必修课" (general education).
+    pub by_category: BTreeMap<String, Credits>,
+}
+
+/// Letter and level grades that fail a course.
+const FAILED_LETTERS: &[&str] = &["F", "不及格"];
+
+pub fn status(course: &Course) -> Status {
+    match &course.score {
+        None | Some(Score::Deferred) => Status::Pending,
+        Some(Score::Withdrawn) => Status::Withdrawn,
+        Some(Score::Fail) | Some(Score::Absent) => Status::Failed,
+        Some(Score::Numeric(score)) if *score < 60.0 => Status::Failed,
+        Some(Score::Letter(letter)) if FAILED_LETTERS.contains(&letter.as_str()) =>
+            Status::Failed,
+        Some(_) => Status::Earned,
+    }
+}
+
+impl Credits {
+    fn add(&mut self, status: Status, hours: f32) {
+        match status {
+            Status::Earned => self.earned += hours,
+            Status::Failed => self.failed += hours,
+            Status::Withdrawn => self.withdrawn += hours,
+            Status::Pending => self.pending += hours,
+        }
+    }
+}
+
+pub fn summarize(courses: &[Course]) -> Summary {
+    let mut summary = Summary::default();
+    for course in courses {
+        let status = status(course);
+        summary.total.add(status, course.hours);
+        summary.by_type.entry(course.course_type.clone())
+            .or_default()
+            .add(status, course.hours);
+        summary.by_category.entry(course.category.clone())
+            .or_default()
+            .add(status, course.hours);
+    }
+    summary
+}
+
+#[test]
+fn test_summarize() {
+    let course = |score: &str, hours: &str, course_type: &str, category: &str| {
+        let cells = ["2018-2019-1", "CODE", "NAME", "", score, "", hours, "考试",
+                     course_type, category];
+        Course::from_cells(&cells).unwrap()
+    };
+    let courses = vec![
+        course("90", "3", "必修", "专业核心"),
+        course("A-", "2", "选修", "通识选修"),
+        course("45", "3", "必修", "专业核心"),
+        course("W", "2", "选修", "通识选修"),
+        course("缓考", "1", "必修", "通识必修"),
+        course("缺考", "4", "必修", "专业核心"),
+    ];
+    let summary = summarize(&courses);
+    assert_eq!(summary.total, Credits { earned: 5.0, failed: 7.0, withdrawn: 2.0, pending: 1.0 });
+    assert_eq!(summary.by_type["必修"].earned, 3.0);
+    assert_eq!(summary.by_type["选修"].withdrawn, 2.0);
+    assert_eq!(summary.by_category["专业核心"].failed, 7.0);
+    assert_eq!(summary.by_category["通识必修"].pending, 1.0);
+}
@@ -0,0 +1,101 @@
+//! Changes between two snapshots of the grade list, such as a newly
+//! published score.
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{Course, Term};
+
+/// A difference of one course between the old and the new snapshot.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    Added(Course),
+    /// Score, grade or point changed.
+    Changed { old: Box<Course>, new: Box<Course> },
+    Removed(Course),
+}
+
+impl Change {
+    /// The course as it is now, or as it was before removed.
+    pub fn course(&self) -> &Course {
+        match self {
+            Change::Added(course) | Change::Removed(course) => course,
+            Change::Changed { new, .. } => new,
+        }
+    }
+}
+
+/// Rows are matched by term and code, then by their order among rows
+/// of the same term and code. Changes come in the order of `new`,
+/// followed by removed rows in the order of `old`.
+pub fn diff(old: &[Course], new: &[Course]) -> Vec<Change> {
+    let mut olds: HashMap<(Term, &str, usize), &Course> = keyed(old).collect();
+    let mut changes = Vec::new();
+    for (key, course) in keyed(new) {
+        match olds.remove(&key) {
+            None => changes.push(Change::Added(course.clone())),
+            Some(old) if old.score != course.score
+                || old.grade != course.grade
+                || old.point != course.point =>
+            {
+                let (old, new) = (Box::new(old.clone()), Box::new(course.clone()));
+                changes.push(Change::Changed { old, new });
+            }
+            Some(_) => (),
+        }
+    }
+    changes.extend(keyed(old)
+        .filter(|(key, _)| olds.contains_key(key))
+        .map(|(_, course)| Change::Removed(course.clone())));
+    changes
+}
+
+/// Courses by term, code and the count of earlier rows with the same.
+fn keyed(courses: &[Course]) -> impl Iterator<Item = ((Term, &str, usize), &Course)> {
+    let mut seen: HashMap<(Term, &str), usize> = HashMap::new();
+    courses.iter().map(move |course| {
+        let nth = seen.entry((course.term, &course.code)).or_default();
+        *nth += 1;
+        ((course.term, course.code.as_str(), *nth - 1), course)
+    })
+}
+
+#[test]
+fn test_diff() {
+    let course = |term, code, score, point| {
+        Course::from_cells(&[term, code, "NAME", "", score, point, "3", "考试"]).unwrap()
+    };
+    let old = vec![
+        course("2018-2019-1", "CS102A", "93", "4.0"),
+        course("2018-2019-2", "CS203", "", ""),
+        course("2018-2019-2", "GE131", "缓考", ""),
+        course("2018-2019-2", "PE102", "P", ""),
+    ];
+    assert!(diff(&old, &old).is_empty());
+
+    let new = vec![
+        course("2018-2019-1", "CS102A", "93", "4.0"),
+        course("2018-2019-2", "CS203", "90", "3.7"),
+        course("2018-2019-2", "GE131", "缓考", ""),
+        course("2019-2020-1", "GE131", "85", "3.0"),
+        course("2018-2019-2", "GE131", "80", "3.0"),
+    ];
+    let changes = diff(&old, &new);
+    assert_eq!(changes.len(), 4);
+    match &changes[0] {
+        Change::Changed { old, new } => {
+            assert_eq!((old.code.as_str(), old.point), ("CS203", None));
+            assert_eq!(new.point, Some(3.7));
+        }
+        change => panic!("unexpected {:?}", change),
+    }
+    assert!(matches!(&changes[1], Change::Added(course) if course.term.to_string() == "2019-2020-1"));
+    assert!(matches!(&changes[2], Change::Added(course) if course.point == Some(3.0)));
+    assert!(matches!(&changes[3], Change::Removed(course) if course.code == "PE102"));
+    assert_eq!(changes[3].course().code, "PE102");
+
+    let json = serde_json::to_value(&changes[0]).unwrap();
+    assert_eq!(json["change"], "changed");
+    assert_eq!(json["new"]["score"], "90");
+    assert_eq!(serde_json::to_value(&changes[1]).unwrap()["change"], "added");
+}
@@ -0,0 +1,101 @@
+use reqwest::{header::LOCATION, Response, Url};
+use url::ParseError;
+
+const URL_CAS_LOGIN: &str = "https://cas.sustech.edu.cn/cas/login";
+const URL_JSXSD: &str = "https://jwxt.sustech.edu.cn/jsxsd/";
+
+const PATH_COURSE_FORM: &str = "kscj/cjcx_query";
+const PATH_COURSE_QUERY: &str = "kscj/cjcx_list";
+const PATH_TIMETABLE: &str = "xskb/xskb_list.do";
+const PATH_EXAM_LIST: &str = "xsks/xsksap_list";
+
+/// URLs of the CAS and jwxt pages to visit.
+///
+/// Defaults to SUSTech. Other schools running the QiangZhi `jsxsd` system
+/// behind CAS can be reached with `Endpoints::new`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    pub cas_login: Url,
+    /// Grade query form, also the CAS service to login to.
+    pub course_form: Url,
+    pub course_query: Url,
+    /// Weekly timetable of a term.
+    pub timetable: Url,
+    /// Exam arrangements of a term.
+    pub exam_list: Url,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Endpoints::new(URL_CAS_LOGIN, URL_JSXSD).expect("invalid default URL")
+    }
+}
+
+impl Endpoints {
+    /// Build from the CAS login URL and the base URL of `jsxsd`, such as
+    /// "https://jwxt.sustech.edu.cn/jsxsd/".
+    pub fn new(cas_login: &str, jsxsd: &str) -> Result<Self, ParseError> {
+        let mut jsxsd = Url::parse(jsxsd)?;
+        if !jsxsd.path().ends_with('/') {
+            let path = format!("{}/", jsxsd.path());
+            jsxsd.set_path(&path);
+        }
+        Ok(Endpoints {
+            cas_login: Url::parse(cas_login)?,
+            course_form: jsxsd.join(PATH_COURSE_FORM)?,
+            course_query: jsxsd.join(PATH_COURSE_QUERY)?,
+            timetable: jsxsd.join(PATH_TIMETABLE)?,
+            exam_list: jsxsd.join(PATH_EXAM_LIST)?,
+        })
+    }
+
+    /// Move CAS and jwxt to other servers, such as a local mock, keeping
+    /// the path of each page.
+    pub fn with_origins(mut self, cas: &Url, jwxt: &Url) -> Self {
+        let replace = |url: &mut Url, origin: &Url| {
+            url.set_scheme(origin.scheme()).ok();
+            url.set_host(origin.host_str()).ok();
+            url.set_port(origin.port()).ok();
+        };
+        replace(&mut self.cas_login, cas);
+        replace(&mut self.course_form, jwxt);
+        replace(&mut self.course_query, jwxt);
+        replace(&mut self.timetable, jwxt);
+        replace(&mut self.exam_list, jwxt);
+        self
+    }
+
+    pub(crate) fn is_cas_login(&self, url: &Url) -> bool {
+        url.origin() == self.cas_login.origin() && url.path() == self.cas_login.path()
+    }
+
+    /// The service URL carrying a ticket, if CAS redirects there.
+    pub(crate) fn ticket_redirect(&self, resp: &Response) -> Option<Url> {
+        if !resp.status().is_redirection() {
+            return None;
+        }
+        let location = resp.headers().get(LOCATION)?.to_str().ok()?;
+        let url = resp.url().join(location).ok()?;
+        let has_ticket = url.query_pairs()
+            .any(|(key, value)| key == "ticket" && !value.is_empty());
+        if url.origin() == self.course_form.origin() && has_ticket {
+            Some(url)
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn test_endpoints() {
+    let endpoints = Endpoints::default();
+    assert_eq!(endpoints.course_form.as_str(),
+               "https://jwxt.sustech.edu.cn/jsxsd/kscj/cjcx_query");
+    assert_eq!(Endpoints::new(URL_CAS_LOGIN, "https://jwxt.sustech.edu.cn/jsxsd"), Ok(endpoints));
+
+    let mock = Url::parse("http://127.0.0.1:8080/").unwrap();
+    let endpoints = Endpoints::default().with_origins(&mock, &mock);
+    assert_eq!(endpoints.cas_login.as_str(), "http://127.0.0.1:8080/cas/login");
+    assert_eq!(endpoints.course_query.as_str(), "http://127.0.0.1:8080/jsxsd/kscj/cjcx_list");
+    assert_eq!(endpoints.timetable.as_str(), "http://127.0.0.1:8080/jsxsd/xskb/xskb_list.do");
+}
@@ -0,0 +1,115 @@
+use failure::Fail;
+use reqwest::StatusCode;
+use select::{
+    document::Document,
+    predicate::{Attr, Class, Name, Predicate},
+};
+
+/// Error of talking to CAS and jwxt.
+#[derive(Debug, Fail)]
+pub enum CourseError {
+    /// Failed to connect, or the server returned an error status.
+    #[fail(display = "network error: {}", _0)]
+    Network(#[cause] reqwest::Error),
+    #[fail(display = "cannot login: {}", _0)]
+    Login(#[cause] LoginError),
+    /// The jwxt session is no longer valid, login again.
+    #[fail(display = "session expired")]
+    SessionExpired,
+    /// A page is not in the expected shape.
+    #[fail(display = "cannot parse {}: {}", page, message)]
+    Parse { page: &'static str, message: String },
+    /// Invalid options given to `UserAgentBuilder`, or a runtime of the
+    /// blocking API that fails to start.
+    #[fail(display = "invalid config: {}", _0)]
+    Config(String),
+}
+
+/// Why CAS rejected a login, with the message it shows to the user.
+#[derive(Debug, Clone, PartialEq, Eq, Fail)]
+pub enum LoginError {
+    #[fail(display = "invalid username or password: {}", _0)]
+    InvalidCredentials(String),
+    #[fail(display = "account locked: {}", _0)]
+    AccountLocked(String),
+    #[fail(display = "captcha required: {}", _0)]
+    CaptchaRequired(String),
+    #[fail(display = "CAS unavailable: {}", _0)]
+    ServiceUnavailable(String),
+    #[fail(display = "{}", _0)]
+    Other(String),
+}
+
+impl From<reqwest::Error> for CourseError {
+    fn from(err: reqwest::Error) -> Self {
+        CourseError::Network(err)
+    }
+}
+
+impl From<LoginError> for CourseError {
+    fn from(err: LoginError) -> Self {
+        CourseError::Login(err)
+    }
+}
+
+/// Keywords of each kind of CAS message, in lower case.
+const LOCKED_KEYWORDS: &[&str] = &["locked", "disabled", "锁定", "冻结", "禁用"];
+const CAPTCHA_KEYWORDS: &[&str] = &["captcha", "验证码"];
+const CREDENTIALS_KEYWORDS: &[&str] = &[
+    "credential", "password", "authentic", "认证信息无效", "密码", "用户名",
+];
+
+impl LoginError {
+    /// Classify the login page CAS returned along with an error.
+    pub(crate) fn from_response(status: StatusCode, doc: &Document) -> Self {
+        let message = doc
+            .find(Attr("id", "msg").or(Class("errors")).or(Class("alert-danger")))
+            .next()
+            .map(|node| node.text().split_whitespace().collect::<Vec<_>>().join(" "))
+            .unwrap_or_default();
+        let lower = message.to_lowercase();
+        let has = |keywords: &[&str]| keywords.iter().any(|k| lower.contains(k));
+        let captcha_field = doc
+            .find(Attr("id", "fm1").descendant(Name("input")))
+            .filter_map(|input| input.attr("name"))
+            .any(|name| CAPTCHA_KEYWORDS.contains(&name.to_lowercase().as_str()));
+
+        if message.is_empty() && status.is_server_error() {
+            LoginError::ServiceUnavailable(format!("server return {}", status))
+        } else if has(LOCKED_KEYWORDS) {
+            LoginError::AccountLocked(message)
+        } else if has(CAPTCHA_KEYWORDS) || captcha_field {
+            LoginError::CaptchaRequired(message)
+        } else if has(CREDENTIALS_KEYWORDS) {
+            LoginError::InvalidCredentials(message)
+        } else if status.is_server_error() {
+            LoginError::ServiceUnavailable(message)
+        } else if message.is_empty() && status.is_success() {
+            LoginError::Other("no service ticket issued".into())
+        } else if message.is_empty() {
+            LoginError::Other(format!("server return {}", status))
+        } else {
+            LoginError::Other(message)
+        }
+    }
+}
+
+#[test]
+fn test_login_error_from_response() {
+    let page = |msg: &str| Document::from(format!(
+        r#"<form id="fm1"><div id="msg" class="errors"><h2>{}</h2></div>
+           <input name="username"><input name="password"></form>"#, msg).as_str());
+
+    let err = LoginError::from_response(StatusCode::UNAUTHORIZED, &page("认证信息无效。"));
+    assert_eq!(err, LoginError::InvalidCredentials("认证信息无效。".into()));
+    let err = LoginError::from_response(StatusCode::UNAUTHORIZED, &page("This account has been locked."));
+    assert_eq!(err, LoginError::AccountLocked("This account has been locked.".into()));
+    let err = LoginError::from_response(StatusCode::UNAUTHORIZED, &page("请输入验证码"));
+    assert_eq!(err, LoginError::CaptchaRequired("请输入验证码".into()));
+
+    let doc = Document::from(r#"<form id="fm1"><input name="captcha"></form>"#);
+    let err = LoginError::from_response(StatusCode::UNAUTHORIZED, &doc);
+    assert!(matches!(err, LoginError::CaptchaRequired(_)));
+    let err = LoginError::from_response(StatusCode::BAD_GATEWAY, &Document::from(""));
+    assert!(matches!(err, LoginError::ServiceUnavailable(_)));
+}
@@ -0,0 +1,121 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use log::warn;
+use select::{
+    document::Document,
+    predicate::{Attr, Name, Predicate},
+};
+use serde::Serialize;
+
+use crate::CourseError;
+
+/// An exam arrangement of a course.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Exam {
+    pub code: String,
+    pub name: String,
+    /// None if not yet arranged.
+    pub start: Option<NaiveDateTime>,
+    pub end: Option<NaiveDateTime>,
+    pub room: String,
+    /// Empty if seats are not assigned.
+    pub seat: String,
+}
+
+const HEADERS_CODE: &[&str] = &["课程编号", "课程号"];
+const HEADERS_NAME: &[&str] = &["课程名称"];
+const HEADERS_TIME: &[&str] = &["考试时间"];
+const HEADERS_ROOM: &[&str] = &["考场", "考试地点", "考试教室"];
+const HEADERS_SEAT: &[&str] = &["座位号"];
+
+/// Parse `#dataList` of `xsks/xsksap_list`, finding columns by their
+/// headers.
+pub(crate) fn parse_exams(doc: &Document) -> Result<Vec<Exam>, CourseError> {
+    let parse_error = |message: String| CourseError::Parse { page: "exam list", message };
+    if doc.find(Attr("id", "dataList")).next().is_none() {
+        return Err(parse_error("no #dataList table".into()));
+    }
+    let mut rows = doc.find(Attr("id", "dataList").descendant(Name("tr")));
+    let headers: Vec<String> = match rows.next() {
+        Some(header) => header.find(Name("th").or(Name("td")))
+            .map(|cell| cell.text().trim().to_string())
+            .collect(),
+        None => Vec::new(),
+    };
+    let column = |names: &[&str]| headers.iter().position(|header| names.contains(&header.as_str()));
+    let (name, time) = match (column(HEADERS_NAME), column(HEADERS_TIME)) {
+        (Some(name), Some(time)) => (name, time),
+        _ => return Err(parse_error(format!("no name or time column in {:?}", headers))),
+    };
+    let (code, room, seat) = (column(HEADERS_CODE), column(HEADERS_ROOM), column(HEADERS_SEAT));
+
+    Ok(rows.filter_map(|row| {
+        let cells: Vec<String> = row.find(Name("td")).map(|cell| cell.text().trim().to_string())
+            .collect();
+        let cell = |index: Option<usize>| index
+            .and_then(|index| cells.get(index))
+            .cloned()
+            .unwrap_or_default();
+        // Rows such as "no record" span all columns
+        if cells.len() < headers.len() {
+            return None;
+        }
+        let text = cell(Some(time));
+        let (start, end) = match parse_time_range(&text) {
+            Some((start, end)) => (Some(start), Some(end)),
+            None if text.is_empty() => (None, None),
+            None => {
+                warn!("unknown exam time {:?}", text);
+                (None, None)
+            }
+        };
+        Some(Exam {
+            code: cell(code),
+            name: cell(Some(name)),
+            start,
+            end,
+            room: cell(room),
+            seat: cell(seat),
+        })
+    }).collect())
+}
+
+/// Parse exam time such as "2018-11-17 09:00~11:00".
+fn parse_time_range(text: &str) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    let (date, times) = text.trim().split_once(' ')?;
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    let (start, end) = times.trim().split_once('~').or_else(|| times.trim().split_once('-'))?;
+    let time = |text: &str| NaiveTime::parse_from_str(text.trim(), "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(text.trim(), "%H:%M"))
+        .ok();
+    Some((date.and_time(time(start)?), date.and_time(time(end)?)))
+}
+
+#[test]
+fn test_parse_time_range() {
+    let at = |hour, min| NaiveDate::from_ymd_opt(2018, 11, 17).unwrap()
+        .and_hms_opt(hour, min, 0).unwrap();
+    assert_eq!(parse_time_range("2018-11-17 09:00~11:00"), Some((at(9, 0), at(11, 0))));
+    assert_eq!(parse_time_range("2018-11-17 14:00:00-16:00:00"), Some((at(14, 0), at(16, 0))));
+    assert_eq!(parse_time_range("待定"), None);
+}
+
+#[test]
+fn test_parse_exams() {
+    let html = r#"<table id="dataList">
+<tr><th>序号</th><th>课程编号</th><th>课程名称</th><th>考试时间</th><th>考场</th><th>座位号</th></tr>
+<tr><td>1</td><td>CS102A</td><td>计算机程序设计基础A</td><td>2019-01-10 09:00~11:00</td>
+  <td>一教105</td><td>23</td></tr>
+<tr><td>2</td><td>MA101B</td><td>高等数学（上）A</td><td></td><td></td><td></td></tr>
+</table>"#;
+    let exams = parse_exams(&Document::from(html)).unwrap();
+    assert_eq!(exams.len(), 2);
+    assert_eq!(exams[0].code, "CS102A");
+    assert_eq!(exams[0].room, "一教105");
+    assert_eq!(exams[0].seat, "23");
+    assert_eq!(exams[0].end.unwrap().to_string(), "2019-01-10 11:00:00");
+    assert_eq!(exams[1].start, None);
+
+    let html = r#"<table id="dataList"><tr><th>序号</th></tr>
+<tr><td colspan="6">未查询到数据</td></tr></table>"#;
+    assert!(parse_exams(&Document::from(html)).is_err());
+}
@@ -0,0 +1,338 @@
+//! Write courses as JSON, JSON Lines, CSV or a Markdown table.
+//!
+//! ```
+//! # let courses: Vec<sustechcourse::Course> = vec![];
+//! use sustechcourse::export::{Column, Exporter, Format};
+//!
+//! let csv = Exporter::new(Format::Csv)
+//!     .columns(vec![Column::Term, Column::Code, Column::Score])
+//!     .sort_by(vec![Column::Term, Column::Code])
+//!     .to_string(&courses)
+//!     .unwrap();
+//! ```
+use failure::Fail;
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::{Course, Score};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// One array of objects.
+    Json,
+    /// One object per line.
+    JsonLines,
+    /// With a header of column names.
+    Csv,
+    Markdown,
+}
+
+/// A field of `Course`, named as in its JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Code,
+    Term,
+    Name,
+    Grade,
+    Score,
+    Point,
+    Hours,
+    EvalMethod,
+    CourseType,
+    Category,
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "unknown format or column: {}", _0)]
+pub struct ParseExportError(String);
+
+#[derive(Debug, Fail)]
+pub enum ExportError {
+    #[fail(display = "cannot write: {}", _0)]
+    Io(#[cause] io::Error),
+    #[fail(display = "cannot write CSV: {}", _0)]
+    Csv(#[cause] csv::Error),
+    #[fail(display = "cannot write JSON: {}", _0)]
+    Json(#[cause] serde_json::Error),
+}
+
+/// Options of an export, by default every column in table order.
+#[derive(Debug, Clone)]
+pub struct Exporter {
+    format: Format,
+    columns: Vec<Column>,
+    sort_by: Vec<Column>,
+    descending: bool,
+}
+
+impl Format {
+    /// Media type of the output.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::JsonLines => "application/x-ndjson",
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::Markdown => "text/markdown; charset=utf-8",
+        }
+    }
+
+    /// The format of a media type, such as one from an `Accept` header.
+    /// Parameters like `charset` are ignored.
+    pub fn from_media_type(media_type: &str) -> Option<Self> {
+        let essence = media_type.split(';').next().unwrap_or_default().trim();
+        match essence.to_ascii_lowercase().as_str() {
+            "application/json" => Some(Format::Json),
+            "application/x-ndjson" | "application/jsonl" | "application/jsonlines" =>
+                Some(Format::JsonLines),
+            "text/csv" => Some(Format::Csv),
+            "text/markdown" | "text/x-markdown" => Some(Format::Markdown),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = ParseExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "json" => Format::Json,
+            "jsonl" | "ndjson" | "jsonlines" => Format::JsonLines,
+            "csv" => Format::Csv,
+            "md" | "markdown" => Format::Markdown,
+            _ => return Err(ParseExportError(s.to_string())),
+        })
+    }
+}
+
+impl Column {
+    pub const ALL: &'static [Column] = &[
+        Column::Code, Column::Term, Column::Name, Column::Grade, Column::Score,
+        Column::Point, Column::Hours, Column::EvalMethod, Column::CourseType, Column::Category,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Code => "code",
+            Column::Term => "term",
+            Column::Name => "name",
+            Column::Grade => "grade",
+            Column::Score => "score",
+            Column::Point => "point",
+            Column::Hours => "hours",
+            Column::EvalMethod => "eval_method",
+            Column::CourseType => "course_type",
+            Column::Category => "category",
+        }
+    }
+
+    /// Text of the field, the same as in the JSON of `Course`.
+    pub fn value(self, course: &Course) -> String {
+        match self {
+            Column::Code => course.code.clone(),
+            Column::Term => course.term.to_string(),
+            Column::Name => course.name.clone(),
+            Column::Grade => course.grade.clone(),
+            Column::Score => course.score_text(),
+            Column::Point => course.point_text(),
+            Column::Hours => course.hours_text(),
+            Column::EvalMethod => course.eval_method_text(),
+            Column::CourseType => course.course_type.clone(),
+            Column::Category => course.category.clone(),
+        }
+    }
+
+    /// Terms in time order, numbers by value, and others by text. Missing
+    /// scores and points come first.
+    fn compare(self, a: &Course, b: &Course) -> Ordering {
+        let number = |value: Option<f32>, other: Option<f32>| {
+            value.partial_cmp(&other).unwrap_or(Ordering::Equal)
+        };
+        match self {
+            Column::Term => a.term.cmp(&b.term),
+            Column::Point => number(a.point, b.point),
+            Column::Hours => number(Some(a.hours), Some(b.hours)),
+            Column::Score => match (&a.score, &b.score) {
+                (Some(Score::Numeric(a)), Some(Score::Numeric(b))) =>
+                    a.partial_cmp(b).unwrap_or(Ordering::Equal),
+                _ => self.value(a).cmp(&self.value(b)),
+            },
+            _ => self.value(a).cmp(&self.value(b)),
+        }
+    }
+}
+
+impl Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Column {
+    type Err = ParseExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Column::ALL.iter()
+            .find(|column| column.name() == s.trim())
+            .cloned()
+            .ok_or_else(|| ParseExportError(s.to_string()))
+    }
+}
+
+impl Exporter {
+    pub fn new(format: Format) -> Self {
+        Exporter {
+            format,
+            columns: Column::ALL.to_vec(),
+            sort_by: Vec::new(),
+            descending: false,
+        }
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Columns to write, in order.
+    pub fn columns(mut self, columns: Vec<Column>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Sort by the first column, then the next on ties, and so on.
+    /// Courses keep the order of jwxt if not set.
+    pub fn sort_by(mut self, columns: Vec<Column>) -> Self {
+        self.sort_by = columns;
+        self
+    }
+
+    pub fn descending(mut self, descending: bool) -> Self {
+        self.descending = descending;
+        self
+    }
+
+    pub fn write<W: Write>(&self, courses: &[Course], mut writer: W) -> Result<(), ExportError> {
+        let courses = self.sorted(courses);
+        match self.format {
+            Format::Json => {
+                let rows: Vec<_> = courses.iter().map(|course| self.json_row(course)).collect();
+                serde_json::to_writer(&mut writer, &rows)?;
+                writeln!(writer)?;
+            }
+            Format::JsonLines => for course in courses {
+                serde_json::to_writer(&mut writer, &self.json_row(course))?;
+                writeln!(writer)?;
+            }
+            Format::Csv => {
+                let mut csv = csv::Writer::from_writer(writer);
+                csv.write_record(self.columns.iter().map(|column| column.name()))?;
+                for course in courses {
+                    csv.write_record(self.columns.iter().map(|column| column.value(course)))?;
+                }
+                csv.flush()?;
+            }
+            Format::Markdown => {
+                let names: Vec<_> = self.columns.iter().map(|column| column.name()).collect();
+                writeln!(writer, "| {} |", names.join(" | "))?;
+                writeln!(writer, "|{}", "---|".repeat(names.len()))?;
+                for course in courses {
+                    let cells: Vec<_> = self.columns.iter()
+                        .map(|column| column.value(course).replace('|', "\\|"))
+                        .collect();
+                    writeln!(writer, "| {} |", cells.join(" | "))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_string(&self, courses: &[Course]) -> Result<String, ExportError> {
+        let mut buf = Vec::new();
+        self.write(courses, &mut buf)?;
+        Ok(String::from_utf8(buf).expect("export is not UTF-8"))
+    }
+
+    fn sorted<'a>(&self, courses: &'a [Course]) -> Vec<&'a Course> {
+        let mut courses: Vec<_> = courses.iter().collect();
+        if !self.sort_by.is_empty() {
+            courses.sort_by(|a, b| {
+                let order = self.sort_by.iter()
+                    .map(|column| column.compare(a, b))
+                    .find(|order| *order != Ordering::Equal)
+                    .unwrap_or(Ordering::Equal);
+                if self.descending { order.reverse() } else { order }
+            });
+        }
+        courses
+    }
+
+    /// Selected columns, plus the unknown ones of jwxt as `extra`.
+    fn json_row(&self, course: &Course) -> Map<String, Value> {
+        let mut row: Map<_, _> = self.columns.iter()
+            .map(|column| (column.name().to_string(), column.value(course).into()))
+            .collect();
+        if !course.extra.is_empty() {
+            let extra = course.extra.iter()
+                .map(|(key, value)| (key.clone(), value.clone().into()))
+                .collect();
+            row.insert("extra".into(), Value::Object(extra));
+        }
+        row
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(err: io::Error) -> Self {
+        ExportError::Io(err)
+    }
+}
+
+impl From<csv::Error> for ExportError {
+    fn from(err: csv::Error) -> Self {
+        ExportError::Csv(err)
+    }
+}
+
+impl From<serde_json::Error> for ExportError {
+    fn from(err: serde_json::Error) -> Self {
+        ExportError::Json(err)
+    }
+}
+
+#[test]
+fn test_export() {
+    let rows = [
+        ["2018-2019-2", "CS203", "Data | Structures", "A-", "90", "3.7", "3"],
+        ["2018-2019-1", "MA101B", "Calculus", "B+", "86", "3.3", "4"],
+        ["2018-2019-1", "CS102A", "Programming", "A", "93", "4.0", "3"],
+    ];
+    let courses: Vec<Course> = rows.iter()
+        .map(|cells| Course::from_cells(cells).unwrap())
+        .collect();
+    let columns = vec![Column::Code, Column::Score, Column::Name];
+
+    let exporter = Exporter::new(Format::Csv).columns(columns.clone());
+    let csv = exporter.sort_by(vec![Column::Term, Column::Code]).to_string(&courses).unwrap();
+    assert_eq!(csv, "code,score,name\nCS102A,93,Programming\nMA101B,86,Calculus\n\
+                     CS203,90,Data | Structures\n");
+
+    let exporter = Exporter::new(Format::Markdown).columns(columns.clone());
+    let markdown = exporter.sort_by(vec![Column::Score]).descending(true)
+        .to_string(&courses)
+        .unwrap();
+    assert_eq!(markdown, "| code | score | name |\n|---|---|---|\n| CS102A | 93 | Programming |\n\
+                          | CS203 | 90 | Data \\| Structures |\n| MA101B | 86 | Calculus |\n");
+
+    let lines = Exporter::new(Format::JsonLines).to_string(&courses).unwrap();
+    let first: Value = serde_json::from_str(lines.lines().next().unwrap()).unwrap();
+    assert_eq!(first, serde_json::to_value(&courses[0]).unwrap());
+    assert_eq!(lines.lines().count(), 3);
+
+    assert_eq!("jsonl".parse::<Format>().unwrap(), Format::JsonLines);
+    assert_eq!(Format::from_media_type("text/csv; charset=utf-8"), Some(Format::Csv));
+    assert_eq!("eval_method".parse::<Column>().unwrap(), Column::EvalMethod);
+    assert!("raw".parse::<Column>().is_err());
+}
@@ -0,0 +1,187 @@
+//! Credit-weighted GPA over the grade list.
+//!
+//! ```no_run
+//! # let courses: Vec<sustechcourse::Course> = vec![];
+//! use sustechcourse::gpa::Rules;
+//!
+//! let rules = Rules::default();
+//! println!("GPA {:?}", rules.overall(&courses).gpa);
+//! for (term, gpa) in rules.cumulative(&courses) {
+//!     println!("{} {:?}", term, gpa.gpa);
+//! }
+//! ```
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::{Course, Score, Term};
+
+/// Which courses count towards GPA.
+#[derive(Debug, Clone)]
+pub struct Rules {
+    /// Skip pass/fail courses. Otherwise a fail counts as zero points.
+    pub exclude_pass_fail: bool,
+    /// Skip courses carrying no credit hours.
+    pub exclude_zero_credit: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            exclude_pass_fail: true,
+            exclude_zero_credit: true,
+        }
+    }
+}
+
+/// GPA over a set of courses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Gpa {
+    /// `None` if no course counts.
+    pub gpa: Option<f32>,
+    pub credits: f32,
+    /// Sum of grade points times credit hours.
+    pub weighted_points: f32,
+}
+
+/// Overall, per-term and cumulative GPA of the same courses.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub overall: Gpa,
+    pub by_term: BTreeMap<Term, Gpa>,
+    pub cumulative: BTreeMap<Term, Gpa>,
+}
+
+/// Why a course does not count towards GPA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Exclusion {
+    PassFail,
+    ZeroCredit,
+    /// Not graded yet, absent, deferred, and so on.
+    NoPoint,
+}
+
+/// How one course contributes to GPA.
+#[derive(Debug, Clone, Serialize)]
+pub struct Contribution<'a> {
+    pub course: &'a Course,
+    /// Grade point and credit hours counted, if included.
+    pub counted: Result<(f32, f32), Exclusion>,
+}
+
+impl Gpa {
+    fn add(&mut self, point: f32, hours: f32) {
+        self.credits += hours;
+        self.weighted_points += point * hours;
+        self.update();
+    }
+
+    fn merge(&mut self, other: &Gpa) {
+        self.credits += other.credits;
+        self.weighted_points += other.weighted_points;
+        self.update();
+    }
+
+    fn update(&mut self) {
+        self.gpa = if self.credits > 0.0 {
+            Some(self.weighted_points / self.credits)
+        } else {
+            None
+        };
+    }
+}
+
+impl Rules {
+    /// Grade point and credit hours of a course, or why it is excluded.
+    pub fn count(&self, course: &Course) -> Result<(f32, f32), Exclusion> {
+        let pass_fail = matches!(course.score, Some(Score::Pass) | Some(Score::Fail));
+        if pass_fail && self.exclude_pass_fail {
+            return Err(Exclusion::PassFail);
+        }
+        if course.hours <= 0.0 && self.exclude_zero_credit {
+            return Err(Exclusion::ZeroCredit);
+        }
+        match (course.point, &course.score) {
+            (Some(point), _) => Ok((point, course.hours)),
+            (None, Some(Score::Fail)) => Ok((0.0, course.hours)),
+            (None, _) => Err(Exclusion::NoPoint),
+        }
+    }
+
+    pub fn contributions<'a>(&self, courses: &'a [Course]) -> Vec<Contribution<'a>> {
+        courses.iter()
+            .map(|course| Contribution { course, counted: self.count(course) })
+            .collect()
+    }
+
+    pub fn overall(&self, courses: &[Course]) -> Gpa {
+        let mut gpa = Gpa::default();
+        for (point, hours) in courses.iter().filter_map(|c| self.count(c).ok()) {
+            gpa.add(point, hours);
+        }
+        gpa
+    }
+
+    /// GPA of each term on its own.
+    pub fn by_term(&self, courses: &[Course]) -> BTreeMap<Term, Gpa> {
+        let mut terms: BTreeMap<Term, Gpa> = BTreeMap::new();
+        for course in courses {
+            let entry = terms.entry(course.term).or_default();
+            if let Ok((point, hours)) = self.count(course) {
+                entry.add(point, hours);
+            }
+        }
+        terms
+    }
+
+    /// GPA of all courses up to and including each term.
+    pub fn cumulative(&self, courses: &[Course]) -> BTreeMap<Term, Gpa> {
+        let mut total = Gpa::default();
+        self.by_term(courses).into_iter().map(|(term, gpa)| {
+            total.merge(&gpa);
+            (term, total)
+        }).collect()
+    }
+
+    pub fn report(&self, courses: &[Course]) -> Report {
+        Report {
+            overall: self.overall(courses),
+            by_term: self.by_term(courses),
+            cumulative: self.cumulative(courses),
+        }
+    }
+}
+
+#[test]
+fn test_gpa() {
+    let course = |term, score, point, hours| {
+        Course::from_cells(&[term, "CODE", "NAME", "", score, point, hours, "考试"]).unwrap()
+    };
+    let courses = vec![
+        course("2018-2019-1", "95", "4.0", "3"),
+        course("2018-2019-1", "P", "", "1"),
+        course("2018-2019-2", "80", "3.0", "1"),
+        course("2018-2019-2", "NP", "", "2"),
+        course("2018-2019-2", "90", "3.7", "0"),
+        course("2018-2019-2", "缓考", "", "2"),
+    ];
+    let rules = Rules::default();
+    let overall = rules.overall(&courses);
+    assert_eq!(overall.credits, 4.0);
+    assert_eq!(overall.gpa, Some(3.75));
+
+    let by_term = rules.by_term(&courses);
+    let first = "2018-2019-1".parse().unwrap();
+    let second = "2018-2019-2".parse().unwrap();
+    assert_eq!(by_term[&first].gpa, Some(4.0));
+    assert_eq!(by_term[&second].gpa, Some(3.0));
+    assert_eq!(rules.cumulative(&courses)[&second], overall);
+
+    let excluded: Vec<_> = rules.contributions(&courses).into_iter()
+        .filter_map(|c| c.counted.err())
+        .collect();
+    assert_eq!(excluded, [Exclusion::PassFail, Exclusion::PassFail,
+                          Exclusion::ZeroCredit, Exclusion::NoPoint]);
+
+    let rules = Rules { exclude_pass_fail: false, ..Rules::default() };
+    assert_eq!(rules.overall(&courses).gpa, Some(15.0 / 6.0));
+}
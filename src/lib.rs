@@ -1,77 +1,100 @@
-use failure::{Error, Fail};
-use reqwest::{
-    r#async::Client,
-    header::{USER_AGENT, REFERER, HeaderMap},
-};
+#![allow(non_local_definitions)] // emitted by failure_derive
+
+use reqwest::{header::REFERER, Client, RequestBuilder, Url};
 use select::{
     document::Document,
-    node::Node,
     predicate::{Attr, Name, Predicate},
 };
-use futures::Future;
 use log::debug;
-use serde::Serialize;
 use std::collections::HashMap;
 
-const URL_CAS_LOGIN: &str = "https://cas.sustech.edu.cn/cas/login";
-const URL_COURSE_FORM: &str = "https://jwxt.sustech.edu.cn/jsxsd/kscj/cjcx_query";
-const URL_COURSE_QUERY: &str = "https://jwxt.sustech.edu.cn/jsxsd/kscj/cjcx_list";
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod builder;
+pub mod calendar;
+#[cfg(feature = "compat")]
+pub mod compat;
+mod course;
+pub mod credits;
+pub mod diff;
+mod endpoints;
+mod error;
+mod exam;
+pub mod export;
+pub mod gpa;
+#[cfg(feature = "notify")]
+pub mod notify;
+mod relogin;
+mod session;
+mod term;
+mod timetable;
+#[cfg(feature = "watch")]
+pub mod watch;
 
-const USER_AGENT_STRING: &str = "sustechcourse/0.1.0 (citric-acid.com.cn)";
+pub use builder::UserAgentBuilder;
+pub use course::{Course, EvalMethod, Score};
+pub use endpoints::Endpoints;
+pub use error::{CourseError, LoginError};
+pub use exam::Exam;
+pub use relogin::Credentials;
+pub use reqwest::{Certificate, Proxy};
+pub use session::CookieJar;
+pub use term::{ParseTermError, Semester, Term};
+pub use timetable::ClassSession;
 
+use crate::{course::Columns, relogin::Relogin, session::Session};
 
 #[derive(Debug, Clone)]
 pub struct UserAgent {
-    client: Client,
+    session: Session,
+    endpoints: Endpoints,
 }
 
 #[derive(Debug, Clone)]
 pub struct LoginedAgent {
-    client: Client,
-}
-
-#[derive(Debug, Clone, Serialize)]
-pub struct Course {
-    pub code: String,
-    pub term: String,
-    pub name: String,
-    pub grade: String,
-    pub score: String,
-    pub point: String,
-    pub hours: String,
-    pub eval_method: String,
-    pub course_type: String,
-    pub category: String,
+    session: Session,
+    endpoints: Endpoints,
+    relogin: Option<Relogin>,
 }
 
 #[derive(Debug, Clone)]
 pub struct CourseQuery<'a> {
     agent: &'a LoginedAgent,
     form: HashMap<String, String>,
-    years: Vec<String>,
+    years: Vec<Term>,
 }
 
-#[derive(Debug, Fail)]
-enum CourseError {
-    #[fail(display = "cannot login: {}", message)]
-    LoginError { message: String },
+/// How duplicated attempts of the same course are listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayMode {
+    /// Every attempt, including retakes.
+    All,
+    /// Only the best score of each course.
+    Best,
 }
 
-impl From<Client> for UserAgent {
-    fn from(client: Client) -> UserAgent {
-        UserAgent { client }
+impl DisplayMode {
+    fn as_form_value(self) -> &'static str {
+        match self {
+            DisplayMode::All => "all",
+            DisplayMode::Best => "max",
+        }
     }
 }
 
-trait NodeExt {
-    fn text(self) -> String;
+impl Default for UserAgent {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl NodeExt for Option<Node<'_>> {
-    fn text(self) -> String {
-        match self {
-            Some(node) => node.text(),
-            None => String::default(),
+/// The client must not follow redirects itself, otherwise login cannot
+/// see the service ticket from CAS.
+impl From<Client> for UserAgent {
+    fn from(client: Client) -> UserAgent {
+        UserAgent {
+            session: Session::new(client, CookieJar::default()),
+            endpoints: Endpoints::default(),
         }
     }
 }
@@ -93,115 +116,297 @@ impl FormFieldExtract for Document {
 }
 
 impl UserAgent {
+    /// Panics if the HTTP client fails to initialize, use `builder` to
+    /// get an error instead.
     pub fn new() -> Self {
-        let mut headers = HeaderMap::new();
-        headers.insert(USER_AGENT, USER_AGENT_STRING.parse().unwrap());
+        Self::builder().build().expect("fail to init http client")
+    }
+
+    pub fn builder() -> UserAgentBuilder {
+        UserAgentBuilder::new()
+    }
+
+    /// Visit other CAS and jwxt pages than SUSTech's.
+    pub fn with_endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
 
-        let client = Client::builder()
-            .gzip(true)
-            .cookie_store(true)
-            .use_sys_proxy()
-            .default_headers(headers)
-            .build()
-            .expect("fail to init http client");
-        UserAgent { client }
+    /// Send requests to other CAS and jwxt servers, such as a local mock,
+    /// keeping the path of each page.
+    pub fn with_origins(mut self, cas: &Url, jwxt: &Url) -> Self {
+        self.endpoints = self.endpoints.with_origins(cas, jwxt);
+        self
     }
 
-    pub fn login(self, username: String, password: String)
-        -> impl Future<Item=LoginedAgent, Error=Error>
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    /// Continue with cookies from `LoginedAgent::cookies`, such as ones
+    /// saved to disk by an earlier process. They replace the jar of this
+    /// agent. Fails with `SessionExpired` if jwxt no longer accepts them.
+    pub async fn restore(self, cookies: CookieJar) -> Result<LoginedAgent, CourseError> {
+        let UserAgent { session, endpoints } = self;
+        let agent = LoginedAgent {
+            session: session.with_cookies(cookies),
+            endpoints,
+            relogin: None,
+        };
+        let request = agent.session.get(agent.endpoints.course_form.clone());
+        agent.fetch_page(request).await?;
+        Ok(agent)
+    }
+
+    pub async fn login(self, username: String, password: String)
+        -> Result<LoginedAgent, CourseError>
     {
-        let UserAgent { client } = self;
+        let UserAgent { session, endpoints } = self;
         debug!("loging in as {}", username);
+        let mut login_url = endpoints.cas_login.clone();
+        login_url.query_pairs_mut().append_pair("service", endpoints.course_form.as_str());
 
         // Retrive login <form> and all its <input>
-        let doc = client
-            .get(URL_CAS_LOGIN)
-            .query(&[("service", URL_COURSE_FORM)])
-            .send()
-            .and_then(|resp| resp.error_for_status())
-            .and_then(|mut resp| resp.text())
-            .map(|text| text.as_str().into());
-        
-        // Fill the form then post
-        let post = doc.and_then(move |doc: Document| {
-            let mut form = doc.extract_form(Attr("id", "fm1"));
-            debug!("login form retrived {:?}", form.keys());
-            form.insert("username", username.as_ref());
-            form.insert("password", password.as_ref());
-            client.post(URL_CAS_LOGIN)
-                .form(&form)
-                .header(REFERER, URL_CAS_LOGIN)
-                .send()
-                .map(move |resp| (resp, client))
-        }).map_err(|err| err.into());
-
-        // Check response
-        post.and_then(|(resp, client)| {
-            debug!("login form posted {:?}", resp);
-            match resp.error_for_status_ref() {
-                Ok(_) => Ok(LoginedAgent { client }),
-                Err(_) => {
-                    // TODO: extract err message
-                    let message = format!("server return {}", resp.status());
-                    Err(CourseError::LoginError { message }.into())
+        let resp = session.send(session.get(login_url.clone())).await?.error_for_status()?;
+
+        let ticket = match endpoints.ticket_redirect(&resp) {
+            Some(url) => {
+                debug!("already logged in on CAS");
+                url
+            }
+            None => {
+                // Fill the form then post
+                let text = resp.text().await?;
+                let form: HashMap<String, String> = {
+                    let doc = Document::from(text.as_str());
+                    let mut form = doc.extract_form(Attr("id", "fm1"));
+                    debug!("login form retrived {:?}", form.keys());
+                    form.insert("username", username.as_ref());
+                    form.insert("password", password.as_ref());
+                    form.into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+                };
+                let request = session.post(login_url.clone())
+                    .form(&form)
+                    .header(REFERER, login_url.as_str());
+                let resp = session.send(request).await?;
+
+                // Check response, CAS redirects to the service with a ticket
+                // on success, or renders the login form again otherwise.
+                debug!("login form posted {:?}", resp);
+                match endpoints.ticket_redirect(&resp) {
+                    Some(url) => url,
+                    None => {
+                        let status = resp.status();
+                        let text = resp.text().await?;
+                        let err = LoginError::from_response(status, &text.as_str().into());
+                        debug!("login failed: {:?}", err);
+                        return Err(err.into());
+                    }
                 }
             }
-        })
+        };
+
+        // Hand the ticket over to the service
+        debug!("service ticket issued for {}", ticket.path());
+        let resp = session.send(session.get(ticket)).await?.error_for_status()?;
+        if endpoints.is_cas_login(resp.url()) {
+            let message = "service rejected the ticket".to_string();
+            Err(LoginError::Other(message).into())
+        } else {
+            Ok(LoginedAgent { session, endpoints, relogin: None })
+        }
     }
 }
 
 impl LoginedAgent {
-    pub fn all_courses(&mut self) -> impl Future<Item = Vec<Course>, Error = Error> {
-        let doc = self.client
-            .get(URL_COURSE_QUERY)
-            .send()
-            .and_then(|resp| resp.error_for_status())
-            .and_then(|mut resp| resp.text())
-            .map(|text| text.as_str().into())
-            .map_err(|err| err.into());
-        doc.map(|doc: Document| {
-            let rows = Attr("id", "dataList").descendant(Name("tr"));
-            doc.find(rows).skip(1).filter_map(|row| {
-                let mut elems = row.find(Name("td"));
-                elems.next(); // drop column id
-                if let (Some(term), Some(code)) = (elems.next(), elems.next()) {
-                    // First two elem is requried
-                    Some(Course {
-                        term: term.text(),
-                        code: code.text(),
-                        name: elems.next().text(),
-                        grade: elems.next().text(),
-                        score: elems.next().text(),
-                        point: elems.next().text(),
-                        hours: elems.next().text(),
-                        eval_method: elems.next().text(),
-                        course_type: elems.next().text(),
-                        category: elems.next().text(),
-                    })
-                } else {
-                    None
-                }
-            }).collect()
-        })
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    /// Cookies of the login, which can be serialized and later given to
+    /// `UserAgent::restore`.
+    pub fn cookies(&self) -> &CookieJar {
+        self.session.cookies()
+    }
+
+    /// Login again and retry once when the session expires, instead of
+    /// failing with `SessionExpired`.
+    pub fn relogin_with<C: Credentials + 'static>(mut self, credentials: C) -> Self {
+        self.relogin = Some(Relogin::new(credentials));
+        self
     }
+
+    /// Send the request built by `request`, failing with `SessionExpired`
+    /// if CAS answers instead of jwxt. With `relogin_with`, login again
+    /// and send a fresh request once more.
+    async fn fetch<F>(&self, request: F) -> Result<Document, CourseError>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        match (self.fetch_page(request()).await, &self.relogin) {
+            (Err(CourseError::SessionExpired), Some(relogin)) => {
+                let (username, password) = relogin.credentials();
+                debug!("session expired, login again as {}", username);
+                let agent = UserAgent {
+                    session: self.session.clone(),
+                    endpoints: self.endpoints.clone(),
+                };
+                agent.login(username, password).await?;
+                self.fetch_page(request()).await
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Fetch a jwxt page, which turns into the CAS login page once the
+    /// session times out.
+    async fn fetch_page(&self, request: RequestBuilder) -> Result<Document, CourseError> {
+        let (url, doc) = self.session.fetch(request).await?;
+        if self.endpoints.is_cas_login(&url) || doc.find(Attr("id", "fm1")).next().is_some() {
+            debug!("session expired, landed on {}", url);
+            Err(CourseError::SessionExpired)
+        } else {
+            Ok(doc)
+        }
+    }
+
+    /// Load the query form, returning a builder to filter courses with.
+    pub async fn query_course(&self) -> Result<CourseQuery<'_>, CourseError> {
+        let doc = self.fetch(|| self.session.get(self.endpoints.course_form.clone())).await?;
+        if doc.find(Attr("name", "kksj")).next().is_none() {
+            let message = "no term selector".into();
+            return Err(CourseError::Parse { page: "course form", message });
+        }
+        let mut form: HashMap<String, String> = doc
+            .extract_form(Name("form"))
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        for name in &["kksj", "kcxz", "kcmc"] {
+            form.entry(name.to_string()).or_default();
+        }
+        form.insert("xsfs".into(), DisplayMode::All.as_form_value().into());
+        let years = doc
+            .find(Attr("name", "kksj").descendant(Name("option")))
+            .filter_map(|opt| opt.attr("value"))
+            .filter_map(|value| value.parse().ok())
+            .collect();
+        debug!("course form retrived, terms {:?}", years);
+        Ok(CourseQuery { agent: self, form, years })
+    }
+
+    pub async fn all_courses(&mut self) -> Result<Vec<Course>, CourseError> {
+        let doc = self.fetch(|| self.session.get(self.endpoints.course_query.clone())).await?;
+        parse_courses(&doc)
+    }
+
+    /// Classes of each week in the term, one per timetable cell entry.
+    pub async fn timetable(&self, term: Term) -> Result<Vec<ClassSession>, CourseError> {
+        let doc = self.fetch(|| {
+            self.session.get(self.endpoints.timetable.clone())
+                .query(&[("xnxq01id", term.to_string())])
+        }).await?;
+        timetable::parse_timetable(&doc)
+    }
+
+    /// Exam arrangements of the term, including ones without a time yet.
+    pub async fn exams(&self, term: Term) -> Result<Vec<Exam>, CourseError> {
+        let doc = self.fetch(|| {
+            self.session.post(self.endpoints.exam_list.clone())
+                .form(&[("xnxqid", term.to_string())])
+        }).await?;
+        exam::parse_exams(&doc)
+    }
+}
+
+impl CourseQuery<'_> {
+    /// Terms listed on the form.
+    pub fn years(&self) -> &[Term] {
+        &self.years
+    }
+
+    /// Limit to one term.
+    pub fn term(mut self, term: Term) -> Self {
+        self.form.insert("kksj".into(), term.to_string());
+        self
+    }
+
+    /// Limit to one course nature (课程性质), by its value on the form.
+    pub fn nature(mut self, nature: &str) -> Self {
+        self.form.insert("kcxz".into(), nature.into());
+        self
+    }
+
+    /// Limit to course names containing the given text.
+    pub fn name(mut self, name: &str) -> Self {
+        self.form.insert("kcmc".into(), name.into());
+        self
+    }
+
+    pub fn display(mut self, mode: DisplayMode) -> Self {
+        self.form.insert("xsfs".into(), mode.as_form_value().into());
+        self
+    }
+
+    pub async fn send(&self) -> Result<Vec<Course>, CourseError> {
+        debug!("query course with {:?}", self.form);
+        let LoginedAgent { session, endpoints, .. } = self.agent;
+        let doc = self.agent.fetch(|| {
+            session.post(endpoints.course_query.clone())
+                .form(&self.form)
+                .header(REFERER, endpoints.course_form.as_str())
+        }).await?;
+        parse_courses(&doc)
+    }
+}
+
+fn parse_courses(doc: &Document) -> Result<Vec<Course>, CourseError> {
+    if doc.find(Attr("id", "dataList")).next().is_none() {
+        let message = "no #dataList table".into();
+        return Err(CourseError::Parse { page: "course list", message });
+    }
+    let mut rows = doc.find(Attr("id", "dataList").descendant(Name("tr")));
+    let headers = match rows.next() {
+        Some(header) => header.find(Name("th").or(Name("td"))).map(|cell| cell.text()).collect(),
+        None => Vec::new(),
+    };
+    let columns = Columns::new(headers)?;
+    Ok(rows.filter_map(|row| {
+        let cells = row.find(Name("td")).map(|cell| cell.text()).collect();
+        // Rows without a term, such as "no record", are dropped
+        columns.course(cells)
+    }).collect())
+}
+
+#[cfg(test)]
+fn credentials() -> (String, String) {
+    let username = std::env::var("USER").expect("USER not set");
+    let password = std::env::var("PASS").expect("PASS not set");
+    (username, password)
 }
 
-#[test]
-fn test_query_course() {
-    let mut agent = UserAgent::new()
-        .login(env!("USER"), env!("PASS"))
-        .unwrap();
-    let courses = agent.query_course(2018, 01).unwrap();
-    assert!(courses.len() > 0);
+#[cfg(test)]
+#[tokio::test]
+#[ignore = "requires SUSTech account in USER and PASS"]
+async fn test_query_course() {
+    let (username, password) = credentials();
+    let agent = UserAgent::new().login(username, password).await.unwrap();
+    let term = Term::new(2018, Semester::Fall).unwrap();
+    let query = agent.query_course().await.unwrap();
+    assert!(query.years().contains(&term));
+    let courses = query.term(term).send().await.unwrap();
+    assert!(!courses.is_empty());
+    assert!(courses.iter().all(|course| course.term == term));
     println!("courses: {:?}", courses);
 }
 
-#[test]
-fn test_all_courses() {
-    let mut agent = UserAgent::new()
-        .login(env!("USER"), env!("PASS"))
-        .unwrap();
-    let courses = agent.all_courses().unwrap();
-    assert!(courses.len() > 0);
+#[cfg(test)]
+#[tokio::test]
+#[ignore = "requires SUSTech account in USER and PASS"]
+async fn test_all_courses() {
+    let (username, password) = credentials();
+    let mut agent = UserAgent::new().login(username, password).await.unwrap();
+    let courses = agent.all_courses().await.unwrap();
+    assert!(!courses.is_empty());
     println!("courses: {:?}", courses);
 }
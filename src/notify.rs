@@ -0,0 +1,386 @@
+//! Send grade changes to an HTTP webhook, by email over SMTP, or to a
+//! local command.
+//!
+//! Sinks deserialize from configuration tagged by `type`, such as this
+//! in TOML:
+//!
+//! ```toml
+//! [[sinks]]
+//! type = "webhook"
+//! url = "https://example.com/hooks/grades"
+//! headers = { Authorization = "Bearer token" }
+//! timeout = 10
+//!
+//! [[sinks]]
+//! type = "smtp"
+//! host = "smtp.example.com"
+//! username = "bot@example.com"
+//! password = "secret"
+//! from = "Grades <bot@example.com>"
+//! to = ["me@example.com"]
+//!
+//! [[sinks]]
+//! type = "command"
+//! program = "notify-send"
+//! args = ["New grade"]
+//! ```
+use failure::Fail;
+use lettre::{
+    message::header::ContentType,
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message as Email, Tokio1Executor,
+};
+use log::debug;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use url::Url;
+
+use crate::{diff::Change, export::Column, Course};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Sink {
+    Webhook(Webhook),
+    Smtp(Smtp),
+    Command(Command),
+}
+
+/// Seconds a webhook may take, unless configured.
+const DEFAULT_WEBHOOK_TIMEOUT: u64 = 30;
+/// Longest wait for connecting to a webhook, capped by its timeout.
+const WEBHOOK_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// POST the JSON of `Payload` to a URL.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+    pub url: String,
+    /// Extra headers, such as `Authorization`.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Seconds until the request is given up, 30 by default, so that a
+    /// stalled receiver does not hold up other sinks.
+    #[serde(default = "default_webhook_timeout")]
+    pub timeout: u64,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Smtp {
+    pub host: String,
+    /// Defaults to the port of `tls`.
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Such as "Grades <bot@example.com>".
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpTls {
+    /// TLS from the start, on port 465.
+    Tls,
+    /// Upgrade with STARTTLS, on port 587.
+    #[default]
+    Starttls,
+    /// Plain text on port 25, only for a relay on the same host.
+    None,
+}
+
+/// Run a program with the JSON of `Payload` on its standard input and
+/// fields of the course in `COURSE_*` environment variables.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Command {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// Text of a change for people to read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub subject: String,
+    /// One line per field of the course.
+    pub body: String,
+}
+
+/// What webhooks and commands receive.
+#[derive(Debug, Serialize)]
+pub struct Payload<'a> {
+    /// "added", "changed" or "removed".
+    pub change: &'static str,
+    pub subject: &'a str,
+    pub message: &'a str,
+    pub course: &'a Course,
+    /// The course before a change.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous: Option<&'a Course>,
+}
+
+#[derive(Debug, Fail)]
+pub enum NotifyError {
+    #[fail(display = "webhook failed: {}", _0)]
+    Webhook(#[cause] reqwest::Error),
+    #[fail(display = "cannot build email: {}", _0)]
+    Email(String),
+    #[fail(display = "SMTP failed: {}", _0)]
+    Smtp(#[cause] lettre::transport::smtp::Error),
+    #[fail(display = "command failed: {}", _0)]
+    Command(String),
+}
+
+/// Field labels of the message body.
+const LABELS: &[(Column, &str)] = &[
+    (Column::Term, "Term"),
+    (Column::Code, "Code"),
+    (Column::Name, "Name"),
+    (Column::Score, "Score"),
+    (Column::Grade, "Grade"),
+    (Column::Point, "Point"),
+    (Column::Hours, "Credits"),
+    (Column::EvalMethod, "Evaluation"),
+    (Column::CourseType, "Type"),
+    (Column::Category, "Category"),
+];
+
+/// Leaves out values of headers and the URL after its host, either of
+/// which may hold a secret.
+impl fmt::Debug for Webhook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let origin = match Url::parse(&self.url) {
+            Ok(url) => url.origin().ascii_serialization(),
+            Err(_) => "<invalid>".to_string(),
+        };
+        f.debug_struct("Webhook")
+            .field("url", &origin)
+            .field("headers", &self.headers.keys().collect::<Vec<_>>())
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+/// Leaves out the password.
+impl fmt::Debug for Smtp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Smtp")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("tls", &self.tls)
+            .field("username", &self.username)
+            .field("from", &self.from)
+            .field("to", &self.to)
+            .finish()
+    }
+}
+
+impl Message {
+    pub fn new(change: &Change) -> Self {
+        let course = change.course();
+        let score = Column::Score.value(course);
+        let previous = match change {
+            Change::Changed { old, .. } => Column::Score.value(old),
+            _ => String::new(),
+        };
+        let subject = match change {
+            Change::Added(_) if score.is_empty() => format!("New course: {}", course.name),
+            Change::Removed(_) => format!("Course removed: {}", course.name),
+            _ if previous.is_empty() => format!("New grade: {} {}", course.name, score),
+            _ => format!("Grade changed: {} {} -> {}", course.name, previous, score),
+        };
+        let mut lines: Vec<String> = LABELS.iter()
+            .map(|(column, label)| (label, column.value(course)))
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(label, value)| format!("{}: {}", label, value))
+            .collect();
+        if !previous.is_empty() {
+            lines.push(format!("Previous score: {}", previous));
+        }
+        Message { subject, body: lines.join("\n") }
+    }
+}
+
+impl<'a> Payload<'a> {
+    pub fn new(change: &'a Change, message: &'a Message) -> Self {
+        let (name, previous) = match change {
+            Change::Added(_) => ("added", None),
+            Change::Changed { old, .. } => ("changed", Some(&**old)),
+            Change::Removed(_) => ("removed", None),
+        };
+        Payload {
+            change: name,
+            subject: &message.subject,
+            message: &message.body,
+            course: change.course(),
+            previous,
+        }
+    }
+}
+
+impl Sink {
+    pub async fn send(&self, change: &Change) -> Result<(), NotifyError> {
+        let message = Message::new(change);
+        let payload = Payload::new(change, &message);
+        match self {
+            Sink::Webhook(webhook) => webhook.send(&payload).await,
+            Sink::Smtp(smtp) => smtp.send(&message).await,
+            Sink::Command(command) => command.run(&payload).await,
+        }
+    }
+}
+
+fn default_webhook_timeout() -> u64 {
+    DEFAULT_WEBHOOK_TIMEOUT
+}
+
+impl Webhook {
+    pub fn new(url: &str) -> Self {
+        Webhook {
+            url: url.to_string(),
+            headers: BTreeMap::new(),
+            timeout: DEFAULT_WEBHOOK_TIMEOUT,
+        }
+    }
+
+    async fn send(&self, payload: &Payload<'_>) -> Result<(), NotifyError> {
+        let timeout = Duration::from_secs(self.timeout);
+        let client = Client::builder()
+            .connect_timeout(timeout.min(WEBHOOK_CONNECT_TIMEOUT))
+            .timeout(timeout)
+            .build()
+            .map_err(NotifyError::Webhook)?;
+        let mut request = client.post(&self.url).json(payload);
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        let resp = request.send().await.and_then(|resp| resp.error_for_status())
+            .map_err(NotifyError::Webhook)?;
+        debug!("webhook answered {}", resp.status());
+        Ok(())
+    }
+}
+
+impl Smtp {
+    async fn send(&self, message: &Message) -> Result<(), NotifyError> {
+        let address_error = |err: lettre::address::AddressError| NotifyError::Email(err.to_string());
+        let mut builder = Email::builder()
+            .from(self.from.parse().map_err(address_error)?)
+            .subject(message.subject.as_str())
+            .header(ContentType::TEXT_PLAIN);
+        for to in &self.to {
+            builder = builder.to(to.parse().map_err(address_error)?);
+        }
+        let email = builder.body(message.body.clone())
+            .map_err(|err| NotifyError::Email(err.to_string()))?;
+
+        let mut transport = match self.tls {
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&self.host)
+                .map_err(NotifyError::Smtp)?,
+            SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&self.host)
+                .map_err(NotifyError::Smtp)?,
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&self.host),
+        };
+        if let Some(port) = self.port {
+            transport = transport.port(port);
+        }
+        if let (Some(username), Some(password)) = (&self.username, &self.password) {
+            transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        let resp = transport.build().send(email).await.map_err(NotifyError::Smtp)?;
+        debug!("email sent: {:?}", resp.code());
+        Ok(())
+    }
+}
+
+impl Command {
+    async fn run(&self, payload: &Payload<'_>) -> Result<(), NotifyError> {
+        let error = |err: &dyn fmt::Display| NotifyError::Command(format!("{}: {}", self.program, err));
+        let course = payload.course;
+        let mut command = tokio::process::Command::new(&self.program);
+        command.args(&self.args)
+            .stdin(Stdio::piped())
+            .env_remove("SUSTECH_PASSWORD")
+            .env("COURSE_CHANGE", payload.change)
+            .env("COURSE_SUBJECT", payload.subject);
+        for (column, _) in LABELS {
+            command.env(format!("COURSE_{}", column.name().to_uppercase()), column.value(course));
+        }
+        let mut child = command.spawn().map_err(|err| error(&err))?;
+        let json = serde_json::to_vec(payload).expect("payload serializes");
+        if let Some(mut stdin) = child.stdin.take() {
+            // The program may well exit without reading it
+            stdin.write_all(&json).await.ok();
+        }
+        let status = child.wait().await.map_err(|err| error(&err))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(error(&status))
+        }
+    }
+}
+
+#[test]
+fn test_message() {
+    let course = |score, point| {
+        let cells = ["2018-2019-2", "CS203", "数据结构", "", score, point, "3", "考试", "必修"];
+        Course::from_cells(&cells).unwrap()
+    };
+    let change = Change::Changed { old: Box::new(course("", "")), new: Box::new(course("95", "4")) };
+    let message = Message::new(&change);
+    assert_eq!(message.subject, "New grade: 数据结构 95");
+    assert!(message.body.starts_with("Term: 2018-2019-2\nCode: CS203\nName: 数据结构\nScore: 95\n"));
+    assert!(!message.body.contains("Grade:"));
+    assert!(message.body.ends_with("\nType: 必修"));
+
+    let regraded = Change::Changed { old: Box::new(course("90", "")), new: Box::new(course("95", "")) };
+    let message = Message::new(&regraded);
+    assert_eq!(message.subject, "Grade changed: 数据结构 90 -> 95");
+    assert!(message.body.ends_with("\nPrevious score: 90"));
+
+    let message = Message::new(&Change::Added(course("", "")));
+    assert_eq!(message.subject, "New course: 数据结构");
+    let json = serde_json::to_value(Payload::new(&change, &message)).unwrap();
+    assert_eq!(json["change"], "changed");
+    assert_eq!(json["course"]["score"], "95");
+    assert_eq!(json["previous"]["score"], "");
+}
+
+#[test]
+fn test_sink_config() {
+    let sinks: Vec<Sink> = serde_json::from_str(r#"[
+        {"type": "webhook", "url": "http://127.0.0.1/hook/s3cret",
+            "headers": {"Authorization": "Bearer t0ken"}},
+        {"type": "smtp", "host": "mail", "password": "p", "from": "a@b.c", "to": ["d@e.f"]},
+        {"type": "command", "program": "true"}
+    ]"#).unwrap();
+    let debug = format!("{:?}", sinks[0]);
+    assert!(debug.contains("http://127.0.0.1") && debug.contains("Authorization"));
+    assert!(!debug.contains("s3cret") && !debug.contains("t0ken"));
+    match &sinks[1] {
+        Sink::Smtp(smtp) => {
+            assert_eq!(smtp.tls, SmtpTls::Starttls);
+            assert!(!format!("{:?}", smtp).contains("\"p\""));
+        }
+        sink => panic!("unexpected {:?}", sink),
+    }
+    assert!(matches!(&sinks[2], Sink::Command(command) if command.args.is_empty()));
+    assert!(matches!(&sinks[0], Sink::Webhook(webhook) if webhook.timeout == DEFAULT_WEBHOOK_TIMEOUT));
+
+    for typo in [
+        r#"{"type": "webhook", "url": "http://127.0.0.1/hook", "header": {}}"#,
+        r#"{"type": "smtp", "host": "mail", "user": "u", "from": "a@b.c", "to": []}"#,
+        r#"{"type": "command", "program": "true", "arg": []}"#,
+    ] {
+        assert!(serde_json::from_str::<Sink>(typo).is_err(), "accepted {}", typo);
+    }
+}
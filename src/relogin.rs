@@ -0,0 +1,46 @@
+use std::fmt;
+use std::sync::Arc;
+
+/// Source of the username and password to login again with, once the
+/// session of a `LoginedAgent` expires.
+///
+/// Implemented for a `(username, password)` pair, and for closures
+/// returning one, such as a prompt or a lookup in a keyring.
+pub trait Credentials: Send + Sync {
+    fn credentials(&self) -> (String, String);
+}
+
+impl Credentials for (String, String) {
+    fn credentials(&self) -> (String, String) {
+        self.clone()
+    }
+}
+
+impl<F> Credentials for F
+where
+    F: Fn() -> (String, String) + Send + Sync,
+{
+    fn credentials(&self) -> (String, String) {
+        self()
+    }
+}
+
+/// Shared credentials kept by a `LoginedAgent`, never printed.
+#[derive(Clone)]
+pub(crate) struct Relogin(Arc<dyn Credentials>);
+
+impl Relogin {
+    pub fn new<C: Credentials + 'static>(credentials: C) -> Self {
+        Relogin(Arc::new(credentials))
+    }
+
+    pub fn credentials(&self) -> (String, String) {
+        self.0.credentials()
+    }
+}
+
+impl fmt::Debug for Relogin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Relogin(..)")
+    }
+}
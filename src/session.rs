@@ -0,0 +1,150 @@
+use cookie_store::CookieStore;
+use log::debug;
+use select::document::Document;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use reqwest::{
+    header::{HeaderValue, COOKIE, LOCATION, SET_COOKIE},
+    Client, RequestBuilder, Response, Url,
+};
+use std::sync::{Arc, RwLock};
+
+use crate::error::CourseError;
+
+const MAX_REDIRECTS: usize = 10;
+
+/// Cookies of CAS and jwxt.
+///
+/// Clones share the same cookies, so a jar given to `UserAgentBuilder`
+/// sees every cookie set on the agents built from it.
+///
+/// Serializing keeps session cookies too, as they are what a login is
+/// made of. Expired cookies are dropped.
+#[derive(Debug, Clone, Default)]
+pub struct CookieJar(Arc<RwLock<CookieStore>>);
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&self) {
+        self.0.write().unwrap().clear();
+    }
+
+    fn header_for(&self, url: &Url) -> Option<HeaderValue> {
+        let store = self.0.read().unwrap();
+        let cookies: Vec<_> = store.get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        if cookies.is_empty() {
+            None
+        } else {
+            HeaderValue::from_str(&cookies.join("; ")).ok()
+        }
+    }
+
+    fn store_from(&self, resp: &Response) {
+        let cookies = resp.headers().get_all(SET_COOKIE).iter()
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(|value| cookie::Cookie::parse(value.to_string()).ok());
+        self.0.write().unwrap().store_response_cookies(cookies, resp.url());
+    }
+}
+
+impl Serialize for CookieJar {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let store = self.0.read().unwrap();
+        serializer.collect_seq(store.iter_unexpired())
+    }
+}
+
+impl<'de> Deserialize<'de> for CookieJar {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let cookies = Vec::<cookie_store::Cookie<'static>>::deserialize(deserializer)?;
+        let store = CookieStore::from_cookies(cookies.into_iter().map(Ok), false)
+            .map_err(|err: std::convert::Infallible| de::Error::custom(err))?;
+        Ok(CookieJar(Arc::new(RwLock::new(store))))
+    }
+}
+
+/// HTTP client with its own cookie jar and redirect handling.
+///
+/// Redirects are followed here instead of inside reqwest, so that cookies
+/// set along the way land in the jar, and the redirect from CAS carrying a
+/// service ticket can be stopped at.
+#[derive(Debug, Clone)]
+pub(crate) struct Session {
+    client: Client,
+    cookies: CookieJar,
+}
+
+impl Session {
+    pub fn new(client: Client, cookies: CookieJar) -> Self {
+        Session { client, cookies }
+    }
+
+    pub fn cookies(&self) -> &CookieJar {
+        &self.cookies
+    }
+
+    /// The same client with another jar.
+    pub fn with_cookies(&self, cookies: CookieJar) -> Self {
+        Session { client: self.client.clone(), cookies }
+    }
+
+    pub fn get(&self, url: Url) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post(&self, url: Url) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// Send the request and follow redirects, except the one from CAS
+    /// login to the service with a ticket.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, CourseError> {
+        let mut request = request.build()?;
+        let mut previous = Vec::new();
+        loop {
+            let url = request.url().clone();
+            if let Some(cookie) = self.cookies.header_for(&url) {
+                request.headers_mut().insert(COOKIE, cookie);
+            }
+            let resp = self.client.execute(request).await?;
+            self.cookies.store_from(&resp);
+            previous.push(url);
+            match self.redirect(&resp, &previous) {
+                Some(next) => request = self.client.get(next).build()?,
+                None => return Ok(resp),
+            }
+        }
+    }
+
+    /// Send the request, and parse the page responded along with its URL.
+    pub async fn fetch(&self, request: RequestBuilder) -> Result<(Url, Document), CourseError> {
+        let resp = self.send(request).await?.error_for_status()?;
+        let url = resp.url().clone();
+        let text = resp.text().await?;
+        Ok((url, text.as_str().into()))
+    }
+
+    fn redirect(&self, resp: &Response, previous: &[Url]) -> Option<Url> {
+        if !resp.status().is_redirection() {
+            return None;
+        }
+        let location = resp.headers().get(LOCATION)?.to_str().ok()?;
+        let next = resp.url().join(location).ok()?;
+        // CAS login is the only page asked with a service
+        let from_cas = previous.first()
+            .is_some_and(|url| url.query_pairs().any(|(key, _)| key == "service"));
+        let has_ticket = next.query_pairs().any(|(key, _)| key == "ticket");
+        if from_cas && has_ticket {
+            None
+        } else if previous.len() > MAX_REDIRECTS {
+            debug!("too many redirects, stop at {}", resp.url());
+            None
+        } else {
+            Some(next)
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use chrono::{Datelike, FixedOffset, NaiveDate, Utc};
+use failure::Fail;
+use serde::{Serialize, Serializer};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// An academic term, written as "2018-2019-1" on jwxt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Term {
+    start_year: u16,
+    semester: Semester,
+}
+
+/// Semesters in the order they take place within an academic year.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Semester {
+    Fall,
+    Spring,
+    Summer,
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "invalid term: {}", _0)]
+pub struct ParseTermError(String);
+
+impl Semester {
+    fn number(self) -> u8 {
+        match self {
+            Semester::Fall => 1,
+            Semester::Spring => 2,
+            Semester::Summer => 3,
+        }
+    }
+}
+
+impl Term {
+    /// `None` if `start_year` is `u16::MAX`, whose end year does not fit.
+    pub fn new(start_year: u16, semester: Semester) -> Option<Self> {
+        if start_year == u16::MAX {
+            return None;
+        }
+        Some(Term { start_year, semester })
+    }
+
+    pub fn start_year(self) -> u16 {
+        self.start_year
+    }
+
+    pub fn end_year(self) -> u16 {
+        self.start_year + 1
+    }
+
+    pub fn semester(self) -> Semester {
+        self.semester
+    }
+
+    /// The term a given date falls in. Fall runs Sep to Jan, spring Feb
+    /// to Jun, and summer Jul to Aug. Years out of the range of `Term` are
+    /// clamped to it.
+    pub fn from_date(date: NaiveDate) -> Self {
+        let term = |year: i32, semester| {
+            let start_year = year.clamp(0, (u16::MAX - 1).into()) as u16;
+            Term { start_year, semester }
+        };
+        match date.month() {
+            9..=12 => term(date.year(), Semester::Fall),
+            1 => term(date.year() - 1, Semester::Fall),
+            2..=6 => term(date.year() - 1, Semester::Spring),
+            _ => term(date.year() - 1, Semester::Summer),
+        }
+    }
+
+    /// The term of today, in China Standard Time.
+    pub fn current() -> Self {
+        let cst = FixedOffset::east_opt(8 * 3600).unwrap();
+        Term::from_date(Utc::now().with_timezone(&cst).naive_local().date())
+    }
+
+    /// `None` before the fall of year 0.
+    pub fn previous(self) -> Option<Self> {
+        match self.semester {
+            Semester::Fall => Term::new(self.start_year.checked_sub(1)?, Semester::Summer),
+            Semester::Spring => Term::new(self.start_year, Semester::Fall),
+            Semester::Summer => Term::new(self.start_year, Semester::Spring),
+        }
+    }
+
+    /// `None` after the last term that `Term::new` accepts.
+    pub fn next(self) -> Option<Self> {
+        match self.semester {
+            Semester::Fall => Term::new(self.start_year, Semester::Spring),
+            Semester::Spring => Term::new(self.start_year, Semester::Summer),
+            Semester::Summer => Term::new(self.start_year + 1, Semester::Fall),
+        }
+    }
+}
+
+impl Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}-{}", self.start_year, self.end_year(), self.semester.number())
+    }
+}
+
+impl FromStr for Term {
+    type Err = ParseTermError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseTermError(s.to_string());
+        let mut parts = s.trim().splitn(3, '-');
+        let mut next = || parts.next().and_then(|part| part.parse::<u16>().ok());
+        let (start_year, end_year, semester) = match (next(), next(), next()) {
+            (Some(start), Some(end), Some(semester)) => (start, end, semester),
+            _ => return Err(err()),
+        };
+        if start_year.checked_add(1) != Some(end_year) {
+            return Err(err());
+        }
+        let semester = match semester {
+            1 => Semester::Fall,
+            2 => Semester::Spring,
+            3 => Semester::Summer,
+            _ => return Err(err()),
+        };
+        Term::new(start_year, semester).ok_or_else(err)
+    }
+}
+
+impl Serialize for Term {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[test]
+fn test_term_parse_and_order() {
+    let term: Term = "2018-2019-1".parse().unwrap();
+    assert_eq!(term, Term::new(2018, Semester::Fall).unwrap());
+    assert_eq!(term.to_string(), "2018-2019-1");
+    assert!("2018-2020-1".parse::<Term>().is_err());
+    assert!("2018-2019-4".parse::<Term>().is_err());
+    assert!("65535-0-1".parse::<Term>().is_err());
+    assert!("65534-65535-3".parse::<Term>().is_ok());
+    assert!("2018-2019-2".parse::<Term>().unwrap() < "2018-2019-3".parse().unwrap());
+    assert!("2018-2019-3".parse::<Term>().unwrap() < "2019-2020-1".parse().unwrap());
+}
+
+#[test]
+fn test_term_neighbours() {
+    let term = Term::new(2018, Semester::Fall).unwrap();
+    assert_eq!(term.previous(), Term::new(2017, Semester::Summer));
+    assert_eq!(term.next(), Term::new(2018, Semester::Spring));
+    assert_eq!(term.next().and_then(Term::next).and_then(Term::next), Term::new(2019, Semester::Fall));
+    assert_eq!(Term::from_date(NaiveDate::from_ymd_opt(2019, 1, 10).unwrap()), term);
+    assert_eq!(Term::from_date(NaiveDate::from_ymd_opt(2019, 3, 1).unwrap()), term.next().unwrap());
+
+    assert_eq!(Term::new(0, Semester::Fall).unwrap().previous(), None);
+    assert_eq!(Term::new(u16::MAX, Semester::Fall), None);
+    let last = Term::new(u16::MAX - 1, Semester::Summer).unwrap();
+    assert_eq!(last.next(), None);
+    assert_eq!(last.to_string().parse::<Term>().unwrap(), last);
+    assert_eq!(Term::from_date(NaiveDate::from_ymd_opt(0, 1, 1).unwrap()), Term::new(0, Semester::Fall).unwrap());
+    let far = Term::from_date(NaiveDate::from_ymd_opt(200_000, 9, 1).unwrap());
+    assert_eq!(far.to_string().parse::<Term>().unwrap(), far);
+}
@@ -0,0 +1,196 @@
+use chrono::Weekday;
+use select::{
+    document::Document,
+    node::Node,
+    predicate::{Attr, Class, Name, Predicate},
+};
+use serde::{Serialize, Serializer};
+
+use crate::CourseError;
+
+/// One weekly class of a course, as shown in a cell of the timetable.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ClassSession {
+    /// Empty if jwxt does not show it.
+    pub code: String,
+    pub name: String,
+    pub teacher: String,
+    pub classroom: String,
+    #[serde(serialize_with = "weekday_number")]
+    pub weekday: Weekday,
+    /// First and last period (节次), starting from 1.
+    pub periods: (u8, u8),
+    /// Teaching weeks the class takes place in, in order.
+    pub weeks: Vec<u8>,
+}
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun,
+];
+
+/// Text of `<font>` in a timetable cell, by its title.
+const TITLE_CODE: &str = "课程编号";
+const TITLES_TEACHER: &[&str] = &["老师", "教师"];
+const TITLE_WEEKS: &str = "周次(节次)";
+const TITLE_CLASSROOM: &str = "教室";
+
+/// Parse the grid of `xskb/xskb_list.do`: rows of periods, columns of
+/// weekdays from Monday, and in each cell the classes separated by a
+/// line of dashes.
+pub(crate) fn parse_timetable(doc: &Document) -> Result<Vec<ClassSession>, CourseError> {
+    let table = doc.find(Attr("id", "kbtable")).next().ok_or_else(|| {
+        let message = "no #kbtable table".into();
+        CourseError::Parse { page: "timetable", message }
+    })?;
+    let mut sessions = Vec::new();
+    for row in table.find(Name("tr")) {
+        let row_periods = row.find(Name("th")).next()
+            .and_then(|header| period_range(&header.text()));
+        for (day, cell) in row.find(Name("td")).enumerate().take(7) {
+            let weekday = WEEKDAYS[day];
+            for content in cell.find(Name("div").and(Class("kbcontent"))) {
+                sessions.extend(parse_cell(content, weekday, row_periods));
+            }
+        }
+    }
+    Ok(sessions)
+}
+
+fn parse_cell(content: Node, weekday: Weekday, row_periods: Option<(u8, u8)>)
+    -> Vec<ClassSession>
+{
+    let mut sessions = Vec::new();
+    let mut session = CellSession::default();
+    for child in content.children() {
+        if let Some(text) = child.as_text() {
+            let text = text.trim_matches(|c: char| c.is_whitespace() || c == '\u{a0}');
+            if text.len() >= 3 && text.chars().all(|c| c == '-') {
+                sessions.extend(session.build(weekday, row_periods));
+                session = CellSession::default();
+            } else if session.name.is_empty() {
+                session.name = text.to_string();
+            }
+        } else if child.is(Name("font")) {
+            let text = child.text().trim().to_string();
+            match child.attr("title").unwrap_or_default() {
+                TITLE_CODE => session.code = text,
+                TITLE_WEEKS => session.weeks = text,
+                TITLE_CLASSROOM => session.classroom = text,
+                title if TITLES_TEACHER.contains(&title) => session.teacher = text,
+                _ => (),
+            }
+        }
+    }
+    sessions.extend(session.build(weekday, row_periods));
+    sessions
+}
+
+#[derive(Default)]
+struct CellSession {
+    code: String,
+    name: String,
+    teacher: String,
+    classroom: String,
+    /// Such as "1-16(周)[01-02节]".
+    weeks: String,
+}
+
+impl CellSession {
+    fn build(self, weekday: Weekday, row_periods: Option<(u8, u8)>) -> Option<ClassSession> {
+        if self.name.is_empty() {
+            return None;
+        }
+        let (weeks, periods) = match self.weeks.find('[') {
+            Some(i) => (&self.weeks[..i], period_range(&self.weeks[i..])),
+            None => (self.weeks.as_str(), None),
+        };
+        Some(ClassSession {
+            code: self.code,
+            name: self.name,
+            teacher: self.teacher,
+            classroom: self.classroom,
+            weekday,
+            periods: periods.or(row_periods)?,
+            weeks: parse_weeks(weeks)?,
+        })
+    }
+}
+
+/// Smallest and largest number in text such as "[01-02节]" or
+/// "第一大节 01,02".
+fn period_range(text: &str) -> Option<(u8, u8)> {
+    let numbers: Vec<u8> = text.split(|c: char| !c.is_ascii_digit())
+        .filter_map(|number| number.parse().ok())
+        .collect();
+    Some((*numbers.iter().min()?, *numbers.iter().max()?))
+}
+
+/// Expand week patterns such as "1-16(周)", "1-15(单周)",
+/// "1,3,5-8(周)" and "2-16双(周)".
+pub(crate) fn parse_weeks(text: &str) -> Option<Vec<u8>> {
+    let odd = text.contains('单');
+    let even = text.contains('双');
+    let text = text.split('(').next()?.trim_end_matches(['单', '双']);
+    let mut weeks = Vec::new();
+    for part in text.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let mut bounds = part.splitn(2, '-').map(|bound| bound.trim().parse::<u8>());
+        let start = bounds.next()?.ok()?;
+        let end = match bounds.next() {
+            Some(end) => end.ok()?,
+            None => start,
+        };
+        weeks.extend((start..=end).filter(|week| {
+            !(odd && week % 2 == 0 || even && week % 2 == 1)
+        }));
+    }
+    weeks.sort_unstable();
+    weeks.dedup();
+    if weeks.is_empty() {
+        None
+    } else {
+        Some(weeks)
+    }
+}
+
+fn weekday_number<S: Serializer>(weekday: &Weekday, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u32(weekday.number_from_monday())
+}
+
+#[test]
+fn test_parse_weeks() {
+    assert_eq!(parse_weeks("1-4(周)"), Some(vec![1, 2, 3, 4]));
+    assert_eq!(parse_weeks("1-7(单周)"), Some(vec![1, 3, 5, 7]));
+    assert_eq!(parse_weeks("2-8双(周)"), Some(vec![2, 4, 6, 8]));
+    assert_eq!(parse_weeks("1,3,6-8(周)"), Some(vec![1, 3, 6, 7, 8]));
+    assert_eq!(parse_weeks("(周)"), None);
+}
+
+#[test]
+fn test_parse_timetable() {
+    let html = r#"<table id="kbtable">
+<tr><th></th><th>星期一</th><th>星期二</th></tr>
+<tr><th>第一大节<br>01,02</th>
+  <td><div class="kbcontent1">short</div><div class="kbcontent">数据结构<br>
+    <font title="老师">张三</font><br><font title="周次(节次)">1-16(周)[01-02节]</font><br>
+    <font title="教室">一教105</font><br>---------------------<br>
+    音乐鉴赏<br><font title="老师">李四</font><br><font title="周次(节次)">1-15(单周)</font><br>
+    <font title="教室">琳恩图书馆</font></div></td>
+  <td><div class="kbcontent">&nbsp;</div></td></tr>
+<tr><th>第二大节<br>03,04</th><td></td>
+  <td><div class="kbcontent">体育I<br><font title="课程编号">PE101</font><br>
+    <font title="周次(节次)">2-8(双周)[03-04-05节]</font></div></td></tr>
+</table>"#;
+    let sessions = parse_timetable(&Document::from(html)).unwrap();
+    assert_eq!(sessions.len(), 3);
+    assert_eq!(sessions[0].name, "数据结构");
+    assert_eq!(sessions[0].teacher, "张三");
+    assert_eq!(sessions[0].classroom, "一教105");
+    assert_eq!(sessions[0].weekday, Weekday::Mon);
+    assert_eq!(sessions[0].weeks.len(), 16);
+    assert_eq!(sessions[1].periods, (1, 2));
+    assert_eq!(sessions[1].weeks, [1, 3, 5, 7, 9, 11, 13, 15]);
+    assert_eq!(sessions[2].code, "PE101");
+    assert_eq!(sessions[2].weekday, Weekday::Tue);
+    assert_eq!(sessions[2].periods, (3, 5));
+    assert_eq!(sessions[2].weeks, [2, 4, 6, 8]);
+}
@@ -0,0 +1,109 @@
+//! Poll the grade list and report what changed since the last poll.
+//!
+//! ```no_run
+//! # async fn run(agent: sustechcourse::LoginedAgent) -> Result<(), sustechcourse::CourseError> {
+//! use std::time::Duration;
+//! use sustechcourse::watch::Watcher;
+//!
+//! let mut watcher = Watcher::new(agent).interval(Duration::from_secs(600));
+//! loop {
+//!     for change in watcher.next().await? {
+//!         println!("{} {:?}", change.course().name, change.course().score);
+//!     }
+//! }
+//! # }
+//! ```
+use log::debug;
+use rand::Rng;
+use std::time::Duration;
+
+use crate::{
+    diff::{diff, Change},
+    Course, CourseError, LoginedAgent,
+};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(30 * 60);
+const DEFAULT_JITTER: Duration = Duration::from_secs(5 * 60);
+
+/// Polls `all_courses` of an agent, keeping the last snapshot to diff
+/// against. Give the agent `relogin_with` to keep it going for days.
+#[derive(Debug, Clone)]
+pub struct Watcher {
+    agent: LoginedAgent,
+    interval: Duration,
+    jitter: Duration,
+    snapshot: Option<Vec<Course>>,
+    polled: bool,
+}
+
+impl Watcher {
+    /// Polls every 30 minutes plus up to 5 minutes of jitter.
+    pub fn new(agent: LoginedAgent) -> Self {
+        Watcher {
+            agent,
+            interval: DEFAULT_INTERVAL,
+            jitter: DEFAULT_JITTER,
+            snapshot: None,
+            polled: false,
+        }
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Each wait is lengthened by a random duration up to this, so that
+    /// many watchers do not poll jwxt at the same moment.
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Diff the first poll against courses fetched before, so that it
+    /// reports changes too. Courses do not deserialize, so this cannot
+    /// pick up from a snapshot of another process.
+    pub fn with_snapshot(mut self, courses: Vec<Course>) -> Self {
+        self.snapshot = Some(courses);
+        self
+    }
+
+    /// Courses of the last successful poll.
+    pub fn snapshot(&self) -> Option<&[Course]> {
+        self.snapshot.as_deref()
+    }
+
+    pub fn agent(&self) -> &LoginedAgent {
+        &self.agent
+    }
+
+    /// Poll until some change is found and return them. The first poll
+    /// is right away, taking the snapshot if none is given; each later
+    /// one waits for the interval.
+    ///
+    /// A failed poll returns the error but keeps the snapshot, so calling
+    /// again continues watching.
+    pub async fn next(&mut self) -> Result<Vec<Change>, CourseError> {
+        loop {
+            if self.polled {
+                tokio::time::sleep(self.delay()).await;
+            }
+            self.polled = true;
+            let courses = self.agent.all_courses().await?;
+            let changes = match &self.snapshot {
+                Some(snapshot) => diff(snapshot, &courses),
+                None => Vec::new(),
+            };
+            debug!("polled {} courses, {} changes", courses.len(), changes.len());
+            self.snapshot = Some(courses);
+            if !changes.is_empty() {
+                return Ok(changes);
+            }
+        }
+    }
+
+    fn delay(&self) -> Duration {
+        let jitter = rand::thread_rng().gen_range(0..=self.jitter.as_millis() as u64);
+        self.interval + Duration::from_millis(jitter)
+    }
+}
@@ -0,0 +1,153 @@
+//! Tests of the sustechcourse binary, built only with its required features.
+#![cfg(all(feature = "cli", feature = "blocking", feature = "watch", feature = "notify"))]
+
+mod support;
+
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use support::MockServer;
+
+fn run(server: &MockServer, args: &[&str]) -> Output {
+    let jsxsd = server.url().join("jsxsd/").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_sustechcourse"))
+        .args(args)
+        .env("SUSTECH_USERNAME", support::USERNAME)
+        .env("SUSTECH_PASSWORD", support::PASSWORD)
+        .env("SUSTECH_CAS_LOGIN", server.url().join("cas/login").unwrap().as_str())
+        .env("SUSTECH_JSXSD", jsxsd.as_str())
+        .env_remove("http_proxy")
+        .env_remove("HTTP_PROXY")
+        .env_remove("all_proxy")
+        .env_remove("ALL_PROXY")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    output
+}
+
+#[test]
+fn test_grades() {
+    let server = MockServer::start();
+    let output = run(&server, &["grades"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), support::COURSES.len() + 1);
+    assert!(stdout.lines().any(|line| line.contains("CS102A") && line.contains("93")));
+
+    let output = run(&server, &["grades", "--term", "2018-2019-2", "--json"]);
+    let courses: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(courses.len(), 2);
+    assert_eq!(courses[0]["code"], "CS203");
+}
+
+#[test]
+fn test_gpa_and_credits() {
+    let server = MockServer::start();
+    let output = run(&server, &["gpa", "--json"]);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["overall"]["credits"], 10.0);
+    assert_eq!(report["by_term"].as_object().unwrap().len(), 2);
+
+    let output = run(&server, &["credits", "--json"]);
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["total"]["earned"], 11.0);
+    assert_eq!(summary["total"]["pending"], 2.0);
+}
+
+#[test]
+fn test_export() {
+    let server = MockServer::start();
+    let args = ["export", "--format", "csv", "--columns", "code,score", "--sort", "score", "--desc"];
+    let output = run(&server, &args);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines.len(), support::COURSES.len() + 1);
+    assert_eq!(lines[..3], ["code,score", "GE131,缓考", "PE101,P"]);
+}
+
+#[test]
+fn test_timetable() {
+    let server = MockServer::start();
+    let output = run(&server, &["timetable", "--term", "2018-2019-1"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 6);
+    let first: Vec<_> = stdout.lines().nth(1).unwrap().split_whitespace().collect();
+    assert_eq!(first[..4], ["Mon", "1-2", "1-16", "CS102A"]);
+    assert!(stdout.lines().any(|line| line.contains("10,12,14-16")));
+
+    let args = ["timetable", "--term", "2018-2019-2", "--start", "2019-02-18"];
+    let stdout = String::from_utf8(run(&server, &args).stdout).unwrap();
+    assert_eq!(stdout.matches("BEGIN:VEVENT").count(), 2);
+    assert!(stdout.contains("DTSTART;TZID=Asia/Shanghai:20190219T080000\r\n"));
+}
+
+#[test]
+fn test_exams() {
+    let server = MockServer::start();
+    let output = run(&server, &["exams", "--term", "2018-2019-1"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 3);
+    assert!(stdout.contains("2019-01-10 09:00-11:00"));
+
+    let stdout = run(&server, &["exams", "--term", "2018-2019-1", "--ics"]).stdout;
+    assert_eq!(String::from_utf8(stdout).unwrap().matches("BEGIN:VEVENT").count(), 2);
+}
+
+#[test]
+fn test_watch_with_config() {
+    let server = MockServer::start();
+    let dir = std::env::temp_dir();
+    let hook = dir.join(format!("sustechcourse-watch-{}.json", std::process::id()));
+    let config = dir.join(format!("sustechcourse-watch-{}.toml", std::process::id()));
+    std::fs::write(&config, format!(r#"
+[account]
+username = "{}"
+password = "{}"
+cas_login = "{}"
+jsxsd = "{}"
+
+[watch]
+interval = 1
+jitter = 0
+
+[[sinks]]
+type = "command"
+program = "sh"
+args = ["-c", "cat > {}"]
+"#, support::USERNAME, support::PASSWORD, server.url().join("cas/login").unwrap(),
+        server.url().join("jsxsd/").unwrap(), hook.display())).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sustechcourse"))
+        .args(["--config", config.to_str().unwrap(), "watch"])
+        .env_remove("SUSTECH_USERNAME")
+        .env_remove("SUSTECH_PASSWORD")
+        .env_remove("SUSTECH_CAS_LOGIN")
+        .env_remove("SUSTECH_JSXSD")
+        .env_remove("http_proxy")
+        .env_remove("HTTP_PROXY")
+        .env_remove("all_proxy")
+        .env_remove("ALL_PROXY")
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Leave time for the first poll, which only takes the snapshot
+    thread::sleep(Duration::from_millis(800));
+    server.publish_score("CS203", "95");
+    let started = Instant::now();
+    while !hook.exists() && started.elapsed() < Duration::from_secs(10) {
+        thread::sleep(Duration::from_millis(50));
+    }
+    thread::sleep(Duration::from_millis(100));
+    child.kill().unwrap();
+    child.wait().unwrap();
+    let mut stdout = String::new();
+    child.stdout.take().unwrap().read_to_string(&mut stdout).unwrap();
+    let payload = std::fs::read_to_string(&hook);
+    std::fs::remove_file(&config).unwrap();
+    std::fs::remove_file(&hook).ok();
+
+    let payload: serde_json::Value = serde_json::from_str(&payload.unwrap()).unwrap();
+    assert_eq!(payload["subject"], "Grade changed: 数据结构与算法分析 90 -> 95");
+    assert_eq!(stdout, "2018-2019-2 CS203 数据结构与算法分析: score 90 -> 95\n");
+}
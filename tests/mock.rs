@@ -0,0 +1,259 @@
+mod support;
+
+use support::MockServer;
+use chrono::Weekday;
+use sustechcourse::{CookieJar, CourseError, LoginError, Score, Semester, Term};
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_login_and_all_courses() {
+    let server = MockServer::start();
+    let login = server.agent().login(support::USERNAME.into(), support::PASSWORD.into());
+    let mut agent = login.await.unwrap();
+    let courses = agent.all_courses().await.unwrap();
+    assert_eq!(courses.len(), support::COURSES.len());
+    assert_eq!(courses[0].code, "CS102A");
+    assert_eq!(courses[0].score, Some(Score::Numeric(93.0)));
+    assert_eq!(courses[2].score, Some(Score::Pass));
+}
+
+#[tokio::test]
+async fn test_query_course_by_term() {
+    let server = MockServer::start();
+    let login = server.agent().login(support::USERNAME.into(), support::PASSWORD.into());
+    let agent = login.await.unwrap();
+    let query = agent.query_course().await.unwrap();
+    let terms: Vec<String> = query.years().iter().map(Term::to_string).collect();
+    assert_eq!(terms, support::TERMS);
+
+    let term = Term::new(2018, Semester::Spring).unwrap();
+    let courses = query.term(term).send().await.unwrap();
+    assert_eq!(courses.len(), 2);
+    assert!(courses.iter().all(|course| course.term == term));
+}
+
+#[tokio::test]
+async fn test_timetable() {
+    let server = MockServer::start();
+    let login = server.agent().login(support::USERNAME.into(), support::PASSWORD.into());
+    let agent = login.await.unwrap();
+    let sessions = agent.timetable(Term::new(2018, Semester::Fall).unwrap()).await.unwrap();
+    assert_eq!(sessions.len(), 5);
+    let first = &sessions[0];
+    assert_eq!((first.code.as_str(), first.name.as_str()), ("CS102A", "计算机程序设计基础A"));
+    assert_eq!((first.teacher.as_str(), first.classroom.as_str()), ("张三", "一教105"));
+    assert_eq!((first.weekday, first.periods), (Weekday::Mon, (1, 2)));
+    assert_eq!(first.weeks, (1..=16).collect::<Vec<_>>());
+
+    let odd = sessions.iter().find(|session| session.weekday == Weekday::Fri).unwrap();
+    assert_eq!(odd.weeks, [1, 3, 5, 7, 9, 11, 13, 15]);
+    let pe: Vec<_> = sessions.iter().filter(|session| session.code == "PE101").collect();
+    assert_eq!(pe.len(), 2, "two classes in one cell");
+    assert_eq!(pe[1].weeks, [10, 12, 14, 15, 16]);
+    assert_eq!(pe[1].classroom, "荔园运动场");
+
+    let sessions = agent.timetable(Term::new(2018, Semester::Spring).unwrap()).await.unwrap();
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions[1].periods, (9, 11));
+    assert_eq!(sessions[1].weeks, [2, 4, 6, 8, 10, 12, 14, 16]);
+}
+
+#[tokio::test]
+async fn test_exams() {
+    let server = MockServer::start();
+    let login = server.agent().login(support::USERNAME.into(), support::PASSWORD.into());
+    let agent = login.await.unwrap();
+    let exams = agent.exams(Term::new(2018, Semester::Fall).unwrap()).await.unwrap();
+    assert_eq!(exams.len(), 2);
+    assert_eq!((exams[0].code.as_str(), exams[0].room.as_str()), ("CS102A", "一教105"));
+    assert_eq!(exams[0].seat, "23");
+    assert_eq!(exams[1].start.unwrap().to_string(), "2019-01-12 14:00:00");
+
+    let exams = agent.exams(Term::new(2018, Semester::Spring).unwrap()).await.unwrap();
+    assert_eq!((exams.len(), exams[0].start), (1, None));
+    assert!(agent.exams(Term::new(2017, Semester::Fall).unwrap()).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_builder_options() {
+    let server = MockServer::start();
+    let agent = server.agent_builder()
+        .user_agent("test-agent/1.0")
+        .connect_timeout(Duration::from_secs(5))
+        .timeout(Duration::from_secs(10))
+        .build()
+        .unwrap();
+    let login = agent.login(support::USERNAME.into(), support::PASSWORD.into());
+    assert!(login.await.is_ok());
+
+    match server.agent_builder().user_agent("bad\nagent").build() {
+        Err(CourseError::Config(_)) => (),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_restore_session() {
+    let server = MockServer::start();
+    let login = server.agent().login(support::USERNAME.into(), support::PASSWORD.into());
+    let agent = login.await.unwrap();
+    let saved = serde_json::to_string(agent.cookies()).unwrap();
+
+    let cookies: CookieJar = serde_json::from_str(&saved).unwrap();
+    let mut agent = server.agent().restore(cookies).await.unwrap();
+    let courses = agent.all_courses().await.unwrap();
+    assert_eq!(courses.len(), support::COURSES.len());
+
+    // Renewed with the CAS login after jwxt session timeout
+    server.expire_sessions();
+    let cookies: CookieJar = serde_json::from_str(&saved).unwrap();
+    assert!(server.agent().restore(cookies).await.is_ok());
+
+    server.expire_sessions();
+    server.expire_cas_logins();
+    let cookies: CookieJar = serde_json::from_str(&saved).unwrap();
+    match server.agent().restore(cookies).await {
+        Err(CourseError::SessionExpired) => (),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Login form shown on the jwxt page itself
+    let agent = server.agent().login(support::USERNAME.into(), support::PASSWORD.into());
+    let saved = serde_json::to_string(agent.await.unwrap().cookies()).unwrap();
+    server.expire_sessions();
+    server.show_login_inline();
+    let cookies: CookieJar = serde_json::from_str(&saved).unwrap();
+    match server.agent().restore(cookies).await {
+        Err(CourseError::SessionExpired) => (),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_login_rejected() {
+    let server = MockServer::start();
+    let login = server.agent().login(support::USERNAME.into(), "wrong".into());
+    match login.await {
+        Err(CourseError::Login(LoginError::InvalidCredentials(_))) => (),
+        other => panic!("unexpected {:?}", other),
+    }
+    let login = server.agent().login(support::LOCKED_USERNAME.into(), "wrong".into());
+    match login.await {
+        Err(CourseError::Login(LoginError::AccountLocked(_))) => (),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_session_expired() {
+    let server = MockServer::start();
+    let login = server.agent().login(support::USERNAME.into(), support::PASSWORD.into());
+    let mut agent = login.await.unwrap();
+
+    server.expire_sessions();
+    server.expire_cas_logins();
+    match agent.all_courses().await {
+        Err(CourseError::SessionExpired) => (),
+        other => panic!("unexpected {:?}", other),
+    }
+    match agent.query_course().await {
+        Err(CourseError::SessionExpired) => (),
+        other => panic!("unexpected {:?}", other.map(|query| query.years().to_vec())),
+    }
+
+    let credentials = (support::USERNAME.to_string(), support::PASSWORD.to_string());
+    let mut agent = agent.relogin_with(credentials);
+    let courses = agent.all_courses().await.unwrap();
+    assert_eq!(courses.len(), support::COURSES.len());
+
+    server.expire_sessions();
+    server.expire_cas_logins();
+    let query = agent.query_course().await.unwrap();
+    server.expire_sessions();
+    server.expire_cas_logins();
+    let term = Term::new(2018, Semester::Fall).unwrap();
+    assert_eq!(query.term(term).send().await.unwrap().len(), 3);
+}
+
+#[cfg(feature = "watch")]
+#[tokio::test]
+async fn test_watcher() {
+    use sustechcourse::{diff::Change, watch::Watcher};
+
+    let server = MockServer::start();
+    let login = server.agent().login(support::USERNAME.into(), support::PASSWORD.into());
+    let mut watcher = Watcher::new(login.await.unwrap())
+        .interval(Duration::from_millis(50))
+        .jitter(Duration::from_millis(20));
+    server.publish_score("GE131", "88");
+    let changes = tokio::time::timeout(Duration::from_millis(300), watcher.next()).await;
+    assert!(changes.is_err(), "changes before the first snapshot");
+    assert_eq!(watcher.snapshot().unwrap().len(), support::COURSES.len());
+
+    server.publish_score("CS203", "95");
+    let changes = watcher.next().await.unwrap();
+    assert_eq!(changes.len(), 1);
+    match &changes[0] {
+        Change::Changed { old, new } => {
+            assert_eq!(old.score, Some(Score::Numeric(90.0)));
+            assert_eq!(new.score, Some(Score::Numeric(95.0)));
+        }
+        change => panic!("unexpected {:?}", change),
+    }
+}
+
+#[cfg(feature = "compat")]
+#[test]
+fn test_compat() {
+    use futures01::Future;
+
+    let server = MockServer::start();
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let _runtime = rt.enter();
+    let login = server.agent().login_compat(support::USERNAME.into(), support::PASSWORD.into());
+    let mut agent = login.wait().unwrap();
+    assert_eq!(agent.all_courses_compat().wait().unwrap().len(), support::COURSES.len());
+    let query = agent.query_course_compat().wait().unwrap();
+    let term = Term::new(2018, Semester::Spring).unwrap();
+    assert_eq!(query.term(term).send_compat().wait().unwrap().len(), 2);
+}
+
+#[test]
+fn test_futures_are_send() {
+    fn assert_send<T: Send>(_: &T) {}
+    let agent = sustechcourse::UserAgent::new();
+    assert_send(&agent.clone().login(String::new(), String::new()));
+    assert_send(&agent.restore(CookieJar::new()));
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_blocking() {
+    let server = MockServer::start();
+    let agent = sustechcourse::blocking::UserAgent::try_from_async(server.agent()).unwrap();
+    let mut agent = agent.login(support::USERNAME.into(), support::PASSWORD.into()).unwrap();
+    assert_eq!(agent.all_courses().unwrap().len(), support::COURSES.len());
+
+    let query = agent.query_course().unwrap();
+    assert_eq!(query.years().len(), support::TERMS.len());
+    let term = Term::new(2018, Semester::Fall).unwrap();
+    assert_eq!(query.term(term).name("体育").send().unwrap().len(), 1);
+    assert_eq!(agent.timetable(term).unwrap().len(), 5);
+    assert_eq!(agent.exams(term).unwrap().len(), 2);
+}
+
+#[cfg(all(feature = "blocking", feature = "watch"))]
+#[test]
+fn test_blocking_watcher() {
+    let server = MockServer::start();
+    let agent = sustechcourse::blocking::UserAgent::from(server.agent());
+    let mut agent = agent.login(support::USERNAME.into(), support::PASSWORD.into()).unwrap();
+    let courses = agent.all_courses().unwrap();
+    server.publish_score("PE101", "NP");
+    let mut watcher = sustechcourse::blocking::Watcher::new(agent)
+        .with_snapshot(courses)
+        .interval(Duration::from_millis(10))
+        .jitter(Duration::ZERO);
+    let changes = watcher.next().unwrap().unwrap();
+    assert_eq!(changes[0].course().score, Some(Score::Fail));
+}
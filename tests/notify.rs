@@ -0,0 +1,201 @@
+//! Sinks against local stand-ins of a webhook receiver, an SMTP server
+//! and a shell command.
+#![cfg(feature = "notify")]
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use sustechcourse::{diff::Change, notify::Sink, Course, EvalMethod, Score};
+
+fn course(score: Option<Score>) -> Course {
+    let mut course = Course::new("2018-2019-2".parse().unwrap(), "CS203", "数据结构与算法分析");
+    course.grade = "A".into();
+    course.score = score;
+    course.point = Some(4.0);
+    course.hours = 3.0;
+    course.eval_method = EvalMethod::Exam;
+    course.course_type = "必修".into();
+    course.category = "专业核心课".into();
+    course
+}
+
+fn change() -> Change {
+    let (old, new) = (course(None), course(Some(Score::Numeric(95.0))));
+    Change::Changed { old: Box::new(old), new: Box::new(new) }
+}
+
+fn sink(config: Value) -> Sink {
+    serde_json::from_value(config).unwrap()
+}
+
+/// Answer one HTTP request with 204, sending back its head and body.
+fn http_receiver() -> (String, mpsc::Receiver<(String, String)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut head = String::new();
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            let lower = line.to_lowercase();
+            if let Some(value) = lower.strip_prefix("content-length:") {
+                length = value.trim().parse().unwrap();
+            }
+            head += &line;
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        reader.get_mut()
+            .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        tx.send((head, String::from_utf8(body).unwrap())).unwrap();
+    });
+    (url, rx)
+}
+
+/// Accept one SMTP session, sending back its commands and message.
+fn smtp_receiver() -> (u16, mpsc::Receiver<(Vec<String>, String)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        let (mut commands, mut data) = (Vec::new(), String::new());
+        writer.write_all(b"220 localhost ESMTP\r\n").unwrap();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap() == 0 {
+                break;
+            }
+            let command = line.trim_end().to_string();
+            let reply: &[u8] = match command.split(' ').next().unwrap().to_uppercase().as_str() {
+                "EHLO" => b"250-localhost\r\n250 AUTH PLAIN LOGIN\r\n",
+                "AUTH" => b"235 2.7.0 Authentication successful\r\n",
+                "DATA" => {
+                    writer.write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n").unwrap();
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line == ".\r\n" {
+                            break;
+                        }
+                        data += &line;
+                    }
+                    b"250 2.0.0 Ok: queued\r\n"
+                }
+                "QUIT" => {
+                    writer.write_all(b"221 2.0.0 Bye\r\n").unwrap();
+                    commands.push(command);
+                    break;
+                }
+                _ => b"250 2.0.0 Ok\r\n",
+            };
+            commands.push(command);
+            writer.write_all(reply).unwrap();
+        }
+        tx.send((commands, data)).unwrap();
+    });
+    (port, rx)
+}
+
+#[tokio::test]
+async fn test_webhook() {
+    let (url, rx) = http_receiver();
+    let webhook = sink(json!({
+        "type": "webhook",
+        "url": url,
+        "headers": { "Authorization": "Bearer hook-token" },
+    }));
+    webhook.send(&change()).await.unwrap();
+
+    let (head, body) = rx.recv().unwrap();
+    assert!(head.starts_with("POST /hook HTTP/1.1\r\n"));
+    assert!(head.to_lowercase().contains("authorization: bearer hook-token\r\n"));
+    let payload: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(payload["change"], "changed");
+    assert_eq!(payload["subject"], "New grade: 数据结构与算法分析 95");
+    assert_eq!(payload["course"]["score"], "95");
+    assert_eq!(payload["previous"]["score"], "");
+    assert!(payload["message"].as_str().unwrap().contains("Category: 专业核心课"));
+}
+
+#[tokio::test]
+async fn test_webhook_rejected() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    drop(listener);
+    let webhook = sink(json!({ "type": "webhook", "url": url }));
+    assert!(webhook.send(&change()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_webhook_timeout() {
+    // Accepts the connection but never answers
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let webhook = sink(json!({ "type": "webhook", "url": url, "timeout": 1 }));
+    let sent = tokio::time::timeout(Duration::from_secs(5), webhook.send(&change())).await;
+    assert!(sent.expect("webhook timed out").is_err());
+    drop(listener);
+}
+
+#[tokio::test]
+async fn test_smtp() {
+    let (port, rx) = smtp_receiver();
+    let smtp = sink(json!({
+        "type": "smtp",
+        "host": "127.0.0.1",
+        "port": port,
+        "tls": "none",
+        "username": "bot",
+        "password": "smtp-secret",
+        "from": "Grades <bot@example.com>",
+        "to": ["me@example.com", "you@example.com"],
+    }));
+    smtp.send(&change()).await.unwrap();
+
+    let (commands, data) = rx.recv().unwrap();
+    assert!(commands.iter().any(|command| command.starts_with("AUTH ")));
+    assert!(commands.contains(&"MAIL FROM:<bot@example.com>".to_string()));
+    assert!(commands.contains(&"RCPT TO:<you@example.com>".to_string()));
+    assert_eq!(commands.last().unwrap(), "QUIT");
+    assert!(data.contains("To: me@example.com, you@example.com\r\n"));
+    assert!(data.contains("Subject: New grade: =?utf-8?"), "subject not encoded: {}", data);
+    assert!(data.contains("Content-Type: text/plain; charset=utf-8\r\n"));
+}
+
+#[tokio::test]
+async fn test_command() {
+    let path = std::env::temp_dir().join(format!("sustechcourse-hook-{}", std::process::id()));
+    let command = sink(json!({
+        "type": "command",
+        "program": "sh",
+        "args": ["-c", "{ cat; echo; env | grep ^COURSE_ | sort; } > \"$0\"", path],
+    }));
+    command.send(&change()).await.unwrap();
+
+    let output = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let mut lines = output.lines();
+    let payload: Value = serde_json::from_str(lines.next().unwrap()).unwrap();
+    assert_eq!(payload["course"]["code"], "CS203");
+    let env: Vec<_> = lines.collect();
+    assert!(env.contains(&"COURSE_CHANGE=changed"));
+    assert!(env.contains(&"COURSE_SCORE=95"));
+    assert!(env.contains(&"COURSE_COURSE_TYPE=必修"));
+
+    let failing = sink(json!({ "type": "command", "program": "false" }));
+    assert!(failing.send(&change()).await.is_err());
+}
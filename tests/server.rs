@@ -0,0 +1,215 @@
+//! Tests of the sustechcourse-server binary, built only with its feature.
+#![cfg(feature = "server")]
+
+mod support;
+
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command};
+use std::thread;
+use std::time::Duration;
+use support::MockServer;
+
+/// The server binary talking to a mock, killed on drop.
+struct Server {
+    child: Child,
+    url: String,
+}
+
+impl Server {
+    fn start(mock: &MockServer) -> Self {
+        let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let jsxsd = mock.url().join("jsxsd/").unwrap();
+        let child = Command::new(env!("CARGO_BIN_EXE_sustechcourse-server"))
+            .env("HTTP_BIND", addr.to_string())
+            .env("SUSTECH_CAS_LOGIN", mock.url().join("cas/login").unwrap().as_str())
+            .env("SUSTECH_JSXSD", jsxsd.as_str())
+            .env_remove("http_proxy")
+            .env_remove("HTTP_PROXY")
+            .env_remove("all_proxy")
+            .env_remove("ALL_PROXY")
+            .spawn()
+            .unwrap();
+        for _ in 0..100 {
+            if TcpStream::connect(addr).is_ok() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        Server { child, url: format!("http://{}", addr) }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.url, path)
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+async fn login(client: &Client, server: &Server) -> String {
+    let body = json!({ "username": support::USERNAME, "password": support::PASSWORD });
+    let resp = client.post(server.url("/v1/login")).json(&body).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = resp.json().await.unwrap();
+    body["token"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_v1_routes() {
+    let mock = MockServer::start();
+    let server = Server::start(&mock);
+    let client = Client::builder().no_proxy().build().unwrap();
+    let token = login(&client, &server).await;
+    let get = |path: &str| client.get(server.url(path)).bearer_auth(&token).send();
+
+    let terms: Value = get("/v1/terms").await.unwrap().json().await.unwrap();
+    assert_eq!(terms, json!(support::TERMS));
+
+    let courses: Value = get("/v1/courses?term=2018-2019-1&type=必修").await.unwrap()
+        .json().await.unwrap();
+    assert_eq!(courses.as_array().unwrap().len(), 3);
+    let courses: Value = get("/v1/courses?category=通识选修课").await.unwrap()
+        .json().await.unwrap();
+    assert_eq!(courses[0]["code"], "GE131");
+
+    let resp = get("/v1/courses?format=csv&columns=code&sort=code").await.unwrap();
+    assert!(resp.headers()["content-type"].to_str().unwrap().starts_with("text/csv"));
+    assert_eq!(resp.text().await.unwrap().lines().nth(1), Some("CS102A"));
+
+    let gpa: Value = get("/v1/gpa").await.unwrap().json().await.unwrap();
+    assert_eq!(gpa["overall"]["credits"], 10.0);
+    let credits: Value = get("/v1/credits").await.unwrap().json().await.unwrap();
+    assert_eq!(credits["total"]["earned"], 11.0);
+
+    let resp = client.post(server.url("/v1/logout")).bearer_auth(&token).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let resp = get("/v1/courses").await.unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["error"], "unauthorized");
+}
+
+#[tokio::test]
+async fn test_v1_timetable() {
+    let mock = MockServer::start();
+    let server = Server::start(&mock);
+    let client = Client::builder().no_proxy().build().unwrap();
+    let token = login(&client, &server).await;
+    let get = |path: &str| client.get(server.url(path)).bearer_auth(&token).send();
+
+    let classes: Value = get("/v1/timetable?term=2018-2019-2").await.unwrap()
+        .json().await.unwrap();
+    assert_eq!(classes[0]["code"], "CS203");
+    assert_eq!(classes[0]["weekday"], 2);
+    let resp = get("/v1/timetable?term=2018-2019-2&start=2019-02-18").await.unwrap();
+    assert!(resp.headers()["content-type"].to_str().unwrap().starts_with("text/calendar"));
+    assert_eq!(resp.text().await.unwrap().matches("BEGIN:VEVENT").count(), 2);
+
+    let body = json!({
+        "username": support::USERNAME,
+        "password": support::PASSWORD,
+        "term": "2018-2019-1",
+        "start": "2018-09-10",
+    });
+    let resp = client.post(server.url("/v1/calendars")).json(&body).send().await.unwrap();
+    let feed: Value = resp.json().await.unwrap();
+    let url = feed["url"].as_str().unwrap();
+    assert!(url.starts_with(&server.url("/v1/calendars/")));
+    assert!(feed["webcal"].as_str().unwrap().starts_with("webcal://"));
+    assert_eq!(feed["max_age"], 20 * 7 * 24 * 60 * 60);
+
+    // Fetched without a bearer token, and after jwxt forgets the login
+    mock.expire_sessions();
+    mock.expire_cas_logins();
+    let resp = client.get(url).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let ics = resp.text().await.unwrap();
+    assert!(ics.contains("X-WR-CALNAME:2018-2019-1\r\n"));
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 5);
+
+    let exams: Value = get("/v1/exams?term=2018-2019-1").await.unwrap().json().await.unwrap();
+    assert_eq!(exams[1]["start"], "2019-01-12T14:00:00");
+    assert_eq!(exams[1]["seat"], "7");
+    let resp = get("/v1/exams?term=2018-2019-1&format=ics").await.unwrap();
+    assert!(resp.headers()["content-type"].to_str().unwrap().starts_with("text/calendar"));
+    assert_eq!(resp.text().await.unwrap().matches("BEGIN:VEVENT").count(), 2);
+    let resp = get("/v1/exams?term=2018-2019-1&format=xml").await.unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = client.delete(url).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let resp = client.get(url).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_v1_errors() {
+    let mock = MockServer::start();
+    let server = Server::start(&mock);
+    let client = Client::builder().no_proxy().build().unwrap();
+
+    let body = json!({ "username": support::USERNAME, "password": "wrong" });
+    let resp = client.post(server.url("/v1/login")).json(&body).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(resp.json::<Value>().await.unwrap()["error"], "login");
+
+    let resp = client.post(server.url("/v1/login")).body("{").send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(resp.json::<Value>().await.unwrap()["error"], "bad_request");
+
+    let resp = client.get(server.url("/v1/nowhere")).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(resp.json::<Value>().await.unwrap()["error"], "not_found");
+
+    let resp = client.get(server.url("/v1/login")).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(resp.json::<Value>().await.unwrap()["error"], "method_not_allowed");
+
+    let token = login(&client, &server).await;
+    let resp = client.get(server.url("/v1/courses?term=2018"))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    mock.expire_sessions();
+    mock.expire_cas_logins();
+    let resp = client.get(server.url("/v1/gpa")).bearer_auth(&token).send().await.unwrap();
+    assert_eq!(resp.json::<Value>().await.unwrap()["error"], "session_expired");
+    let resp = client.get(server.url("/v1/gpa")).bearer_auth(&token).send().await.unwrap();
+    assert_eq!(resp.json::<Value>().await.unwrap()["error"], "unauthorized");
+}
+
+#[tokio::test]
+async fn test_deprecated_routes() {
+    let mock = MockServer::start();
+    let server = Server::start(&mock);
+    let client = Client::builder().no_proxy().build().unwrap();
+    let body = json!({ "username": support::USERNAME, "password": support::PASSWORD });
+
+    let resp = client.post(server.url("/login")).json(&body).send().await.unwrap();
+    let token: Value = resp.json().await.unwrap();
+    let token = token["token"].as_str().unwrap();
+    let courses: Value = client.get(server.url("/courses")).bearer_auth(token).send().await.unwrap()
+        .json().await.unwrap();
+    assert_eq!(courses.as_array().unwrap().len(), support::COURSES.len());
+    let credits: Value = client.get(server.url("/credits")).bearer_auth(token).send().await.unwrap()
+        .json().await.unwrap();
+    assert_eq!(credits["total"]["earned"], 11.0);
+    let resp = client.post(server.url("/logout")).bearer_auth(token).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let credits: Value = client.post(server.url("/credits")).json(&body).send().await.unwrap()
+        .json().await.unwrap();
+    assert_eq!(credits["total"]["earned"], 11.0);
+    let courses: Value = client.post(server.url("/")).json(&body).send().await.unwrap()
+        .json().await.unwrap();
+    assert_eq!(courses[0]["point"], "4.0");
+}
@@ -0,0 +1,390 @@
+//! A local stand-in for CAS and jwxt, serving just enough of their pages
+//! for login, grade queries, timetables and exams to run without network.
+#![allow(dead_code)]
+
+use actix_web::{
+    cookie::Cookie, dev::ServerHandle, http::header::LOCATION, web, App, HttpRequest,
+    HttpResponse, HttpServer,
+};
+use reqwest::Url;
+use std::collections::{HashMap, HashSet};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use sustechcourse::{Endpoints, UserAgent, UserAgentBuilder};
+
+pub const USERNAME: &str = "11510000";
+pub const PASSWORD: &str = "secret";
+/// Account that CAS reports as locked.
+pub const LOCKED_USERNAME: &str = "11519999";
+
+pub const TERMS: &[&str] = &["2018-2019-1", "2018-2019-2"];
+
+/// Cells of the grade table, in column order without the id column.
+pub const COURSES: &[[&str; 10]] = &[
+    ["2018-2019-1", "CS102A", "计算机程序设计基础A", "A", "93", "4.0", "3", "考试", "必修", "专业基础课"],
+    ["2018-2019-1", "MA101B", "高等数学（上）A", "B+", "86", "3.3", "4", "考试", "必修", "通识必修课"],
+    ["2018-2019-1", "PE101", "体育I", "", "P", "", "1", "考查", "必修", "通识必修课"],
+    ["2018-2019-2", "CS203", "数据结构与算法分析", "A-", "90", "3.7", "3", "考试", "必修", "专业核心课"],
+    ["2018-2019-2", "GE131", "音乐鉴赏", "", "缓考", "", "2", "考查", "选修", "通识选修课"],
+];
+
+/// Entries of the timetable: term, weekday from 1 for Monday, row of
+/// the grid from 1, code, name, teacher, weeks and periods, classroom.
+pub const TIMETABLE: &[[&str; 8]] = &[
+    ["2018-2019-1", "1", "1", "CS102A", "计算机程序设计基础A", "张三", "1-16(周)[01-02节]", "一教105"],
+    ["2018-2019-1", "3", "2", "MA101B", "高等数学（上）A", "李四", "1-16(周)[03-04节]", "三教201"],
+    ["2018-2019-1", "5", "2", "MA101B", "高等数学（上）A", "李四", "1-15(单周)[03-04节]", "三教201"],
+    ["2018-2019-1", "4", "4", "PE101", "体育I", "王五", "1-8(周)[07-08节]", "体育馆"],
+    ["2018-2019-1", "4", "4", "PE101", "体育I", "王五", "10,12,14-16(周)[07-08节]", "荔园运动场"],
+    ["2018-2019-2", "2", "1", "CS203", "数据结构与算法分析", "赵六", "1-16(周)[01-02节]", "一教105"],
+    ["2018-2019-2", "4", "5", "GE131", "音乐鉴赏", "钱七", "2-16(双周)[09-11节]", "琳恩图书馆"],
+];
+
+/// Exam arrangements: term, code, name, time, room, seat.
+pub const EXAMS: &[[&str; 6]] = &[
+    ["2018-2019-1", "CS102A", "计算机程序设计基础A", "2019-01-10 09:00~11:00", "一教105", "23"],
+    ["2018-2019-1", "MA101B", "高等数学（上）A", "2019-01-12 14:00~16:00", "三教201", "7"],
+    ["2018-2019-2", "CS203", "数据结构与算法分析", "", "", ""],
+];
+
+const TICKET: &str = "ST-1-mock";
+const TGT: &str = "TGT-1-mock";
+const SESSION: &str = "mock-session";
+
+#[derive(Default)]
+struct State {
+    /// Valid CASTGC cookies
+    tgts: HashSet<String>,
+    /// Valid JSESSIONID cookies of jwxt
+    sessions: HashSet<String>,
+    /// Scores published after `COURSES`, by course code
+    scores: HashMap<String, String>,
+    /// Show the login form in place of jwxt pages without a session,
+    /// instead of redirecting to CAS
+    inline_login: bool,
+}
+
+type SharedState = Arc<Mutex<State>>;
+
+pub struct MockServer {
+    url: Url,
+    server: ServerHandle,
+    state: SharedState,
+}
+
+impl MockServer {
+    pub fn start() -> Self {
+        let state = SharedState::default();
+        let app_state = state.clone();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let sys = actix_rt::System::new();
+            let server = HttpServer::new(move || {
+                App::new()
+                    .app_data(web::Data::new(app_state.clone()))
+                    .service(web::resource("/cas/login")
+                        .route(web::get().to(cas_login_page))
+                        .route(web::post().to(cas_login_post)))
+                    .service(web::resource("/jsxsd/kscj/cjcx_query")
+                        .route(web::get().to(course_form)))
+                    .service(web::resource("/jsxsd/kscj/cjcx_list")
+                        .route(web::get().to(course_list))
+                        .route(web::post().to(course_list_post)))
+                    .service(web::resource("/jsxsd/xskb/xskb_list.do")
+                        .route(web::get().to(timetable)))
+                    .service(web::resource("/jsxsd/xsks/xsksap_list")
+                        .route(web::post().to(exam_list)))
+            })
+                .workers(1)
+                .disable_signals()
+                .bind("127.0.0.1:0")
+                .expect("cannot bind mock server");
+            let addr = server.addrs()[0];
+            let server = server.run();
+            tx.send((addr, server.handle())).unwrap();
+            sys.block_on(server).unwrap();
+        });
+        let (addr, server) = rx.recv().expect("mock server not started");
+        let url = Url::parse(&format!("http://{}/", addr)).unwrap();
+        MockServer { url, server, state }
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// A `UserAgent` talking to this server.
+    pub fn agent(&self) -> UserAgent {
+        self.agent_builder().build().unwrap()
+    }
+
+    pub fn agent_builder(&self) -> UserAgentBuilder {
+        let endpoints = Endpoints::default().with_origins(&self.url, &self.url);
+        UserAgent::builder().no_proxy().endpoints(endpoints)
+    }
+
+    /// Drop all jwxt sessions, as if they timed out.
+    pub fn expire_sessions(&self) {
+        self.state.lock().unwrap().sessions.clear();
+    }
+
+    /// Drop all CAS logins, so that jwxt sessions cannot be renewed
+    /// without the password.
+    pub fn expire_cas_logins(&self) {
+        self.state.lock().unwrap().tgts.clear();
+    }
+
+    /// Answer jwxt pages without a session with the login form itself,
+    /// as some versions of jwxt do, instead of a redirect.
+    pub fn show_login_inline(&self) {
+        self.state.lock().unwrap().inline_login = true;
+    }
+
+    /// Show another score of a course from now on.
+    pub fn publish_score(&self, code: &str, score: &str) {
+        self.state.lock().unwrap().scores.insert(code.into(), score.into());
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        // Stop is sent right away, no need to wait for it
+        drop(self.server.stop(false));
+    }
+}
+
+fn html(body: String) -> HttpResponse {
+    HttpResponse::Ok().content_type("text/html; charset=utf-8").body(body)
+}
+
+fn redirect(location: &str) -> HttpResponse {
+    HttpResponse::Found().insert_header((LOCATION, location)).finish()
+}
+
+fn login_page(message: Option<&str>) -> String {
+    let message = message
+        .map(|msg| format!(r#"<div id="msg" class="errors"><h2>{}</h2></div>"#, msg))
+        .unwrap_or_default();
+    format!(r#"<html><body>
+<form id="fm1" method="post">
+  {}
+  <input id="username" name="username" type="text" value="">
+  <input id="password" name="password" type="password" value="">
+  <input type="hidden" name="execution" value="e1s1">
+  <input type="hidden" name="_eventId" value="submit">
+  <input type="submit" name="submit" value="登录">
+</form>
+</body></html>"#, message)
+}
+
+fn with_ticket(service: &str) -> String {
+    let mut url = Url::parse(service).unwrap();
+    url.query_pairs_mut().append_pair("ticket", TICKET);
+    url.into()
+}
+
+async fn cas_login_page(
+    req: HttpRequest,
+    state: web::Data<SharedState>,
+    query: web::Query<HashMap<String, String>>,
+) -> HttpResponse {
+    let logged_in = req.cookie("CASTGC")
+        .is_some_and(|tgt| state.lock().unwrap().tgts.contains(tgt.value()));
+    match query.get("service") {
+        Some(service) if logged_in => redirect(&with_ticket(service)),
+        _ => html(login_page(None)),
+    }
+}
+
+async fn cas_login_post(
+    state: web::Data<SharedState>,
+    query: web::Query<HashMap<String, String>>,
+    form: web::Form<HashMap<String, String>>,
+) -> HttpResponse {
+    let field = |name: &str| form.get(name).map(String::as_str).unwrap_or_default();
+    if field("execution") != "e1s1" || field("_eventId") != "submit" {
+        return HttpResponse::BadRequest().body("missing hidden fields");
+    }
+    let message = match (field("username"), field("password")) {
+        (LOCKED_USERNAME, _) => "This account has been locked.",
+        (USERNAME, PASSWORD) => {
+            let service = match query.get("service") {
+                Some(service) => service,
+                None => return html(login_page(Some("登录成功"))),
+            };
+            state.lock().unwrap().tgts.insert(TGT.into());
+            return HttpResponse::Found()
+                .cookie(Cookie::build("CASTGC", TGT).path("/cas").finish())
+                .insert_header((LOCATION, with_ticket(service)))
+                .finish();
+        }
+        _ => "认证信息无效。",
+    };
+    HttpResponse::Unauthorized()
+        .content_type("text/html; charset=utf-8")
+        .body(login_page(Some(message)))
+}
+
+/// Check the jwxt session, or handle the ticket redirect from CAS.
+/// Returns the redirect to answer with if the page cannot be shown.
+fn check_session(req: &HttpRequest, state: &SharedState) -> Option<HttpResponse> {
+    let mut state = state.lock().unwrap();
+    let query = req.query_string();
+    if query.contains(&format!("ticket={}", TICKET)) {
+        state.sessions.insert(SESSION.into());
+        return Some(HttpResponse::Found()
+            .cookie(Cookie::build("JSESSIONID", SESSION).path("/jsxsd").finish())
+            .insert_header((LOCATION, req.path()))
+            .finish());
+    }
+    match req.cookie("JSESSIONID") {
+        Some(cookie) if state.sessions.contains(cookie.value()) => None,
+        _ if state.inline_login => Some(html(login_page(None))),
+        _ => {
+            let info = req.connection_info();
+            let service = format!("{}://{}{}", info.scheme(), info.host(), req.path());
+            let mut login = Url::parse(&format!("{}://{}/cas/login", info.scheme(), info.host()))
+                .unwrap();
+            login.query_pairs_mut().append_pair("service", &service);
+            Some(redirect(login.as_str()))
+        }
+    }
+}
+
+async fn course_form(req: HttpRequest, state: web::Data<SharedState>) -> HttpResponse {
+    if let Some(resp) = check_session(&req, &state) {
+        return resp;
+    }
+    let terms: String = TERMS.iter()
+        .map(|term| format!(r#"<option value="{0}">{0}</option>"#, term))
+        .collect();
+    html(format!(r#"<html><body>
+<form id="kscjQueryForm" action="/jsxsd/kscj/cjcx_list" method="post">
+  <select id="kksj" name="kksj"><option value="">---请选择---</option>{}</select>
+  <select id="kcxz" name="kcxz"><option value="">---请选择---</option>
+    <option value="01">公共课</option><option value="02">专业课</option></select>
+  <input type="text" id="kcmc" name="kcmc" value="">
+  <select id="xsfs" name="xsfs"><option value="all">显示全部成绩</option>
+    <option value="max">显示最好成绩</option></select>
+</form>
+</body></html>"#, terms))
+}
+
+fn course_table(state: &SharedState, term: &str, name: &str) -> String {
+    let scores = &state.lock().unwrap().scores;
+    let rows: String = COURSES.iter()
+        .filter(|row| term.is_empty() || row[0] == term)
+        .filter(|row| row[2].contains(name))
+        .enumerate()
+        .map(|(i, row)| {
+            let mut row = *row;
+            if let Some(score) = scores.get(row[1]) {
+                row[4] = score;
+            }
+            let cells: String = row.iter().map(|cell| format!("<td>{}</td>", cell)).collect();
+            format!("<tr><td>{}</td>{}</tr>\n", i + 1, cells)
+        })
+        .collect();
+    format!(r#"<html><body>
+<table id="dataList">
+<tr><th>序号</th><th>开课学期</th><th>课程编号</th><th>课程名称</th><th>等级成绩</th>
+<th>成绩</th><th>绩点</th><th>学分</th><th>考核方式</th><th>课程属性</th><th>课程性质</th></tr>
+{}</table>
+</body></html>"#, rows)
+}
+
+async fn course_list(req: HttpRequest, state: web::Data<SharedState>) -> HttpResponse {
+    match check_session(&req, &state) {
+        None => html(course_table(&state, "", "")),
+        Some(resp) => resp,
+    }
+}
+
+async fn course_list_post(
+    req: HttpRequest,
+    state: web::Data<SharedState>,
+    form: web::Form<HashMap<String, String>>,
+) -> HttpResponse {
+    if let Some(resp) = check_session(&req, &state) {
+        return resp;
+    }
+    let field = |name: &str| form.get(name).map(String::as_str).unwrap_or_default();
+    html(course_table(&state, field("kksj"), field("kcmc")))
+}
+
+/// The weekly grid, each cell holding its classes separated by dashes,
+/// like jwxt renders them.
+fn timetable_grid(term: &str) -> String {
+    let days = ["一", "二", "三", "四", "五", "六", "日"];
+    let mut rows = String::new();
+    for row in 1..=6 {
+        rows += &format!("<tr><th>第{}大节<br>{:02},{:02}</th>", row, row * 2 - 1, row * 2);
+        for day in 1..=days.len() {
+            let classes: Vec<String> = TIMETABLE.iter()
+                .filter(|entry| entry[0] == term)
+                .filter(|entry| entry[1] == day.to_string() && entry[2] == row.to_string())
+                .map(|entry| format!(concat!(
+                    r#"{}<br><font title="课程编号">{}</font><br><font title="老师">{}</font><br>"#,
+                    r#"<font title="周次(节次)">{}</font><br><font title="教室">{}</font><br>"#),
+                    entry[4], entry[3], entry[5], entry[6], entry[7]))
+                .collect();
+            let content = if classes.is_empty() {
+                "&nbsp;".to_string()
+            } else {
+                classes.join("---------------------<br>")
+            };
+            rows += &format!(
+                r#"<td><div class="kbcontent1">{0}</div><div class="kbcontent">{0}</div></td>"#,
+                content);
+        }
+        rows += "</tr>\n";
+    }
+    let header: String = days.iter().map(|day| format!("<th>星期{}</th>", day)).collect();
+    format!(r#"<html><body>
+<table id="kbtable">
+<tr><th></th>{}</tr>
+{}<tr><th>备注</th><td colspan="7">&nbsp;</td></tr>
+</table>
+</body></html>"#, header, rows)
+}
+
+async fn timetable(
+    req: HttpRequest,
+    state: web::Data<SharedState>,
+    query: web::Query<HashMap<String, String>>,
+) -> HttpResponse {
+    if let Some(resp) = check_session(&req, &state) {
+        return resp;
+    }
+    html(timetable_grid(query.get("xnxq01id").map(String::as_str).unwrap_or_default()))
+}
+
+async fn exam_list(
+    req: HttpRequest,
+    state: web::Data<SharedState>,
+    form: web::Form<HashMap<String, String>>,
+) -> HttpResponse {
+    if let Some(resp) = check_session(&req, &state) {
+        return resp;
+    }
+    let term = form.get("xnxqid").map(String::as_str).unwrap_or_default();
+    let rows: String = EXAMS.iter()
+        .filter(|row| row[0] == term)
+        .enumerate()
+        .map(|(i, row)| {
+            let cells: String = row[1..].iter().map(|cell| format!("<td>{}</td>", cell)).collect();
+            format!("<tr><td>{}</td><td>第{}场</td>{}</tr>\n", i + 1, i + 1, cells)
+        })
+        .collect();
+    let rows = if rows.is_empty() {
+        r#"<tr><td colspan="8">未查询到数据</td></tr>"#.to_string()
+    } else {
+        rows
+    };
+    html(format!(r#"<html><body>
+<table id="dataList">
+<tr><th>序号</th><th>考试场次</th><th>课程编号</th><th>课程名称</th><th>考试时间</th>
+<th>考场</th><th>座位号</th></tr>
+{}</table>
+</body></html>"#, rows))
+}